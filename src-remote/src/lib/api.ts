@@ -13,14 +13,39 @@ export interface AccessResponse {
     jwt?: string;
 }
 
+export type Key =
+    'A' | 'B' | 'C' | 'D' | 'E' | 'F' | 'G' | 'H' | 'I' | 'J' | 'K' | 'L' | 'M' |
+    'N' | 'O' | 'P' | 'Q' | 'R' | 'S' | 'T' | 'U' | 'V' | 'W' | 'X' | 'Y' | 'Z' |
+    'Digit0' | 'Digit1' | 'Digit2' | 'Digit3' | 'Digit4' |
+    'Digit5' | 'Digit6' | 'Digit7' | 'Digit8' | 'Digit9' |
+    'F1' | 'F2' | 'F3' | 'F4' | 'F5' | 'F6' | 'F7' | 'F8' | 'F9' | 'F10' | 'F11' | 'F12' |
+    'Minus' | 'Equal' | 'LeftBracket' | 'RightBracket' | 'Backslash' |
+    'Semicolon' | 'Quote' | 'Grave' | 'Comma' | 'Period' | 'Slash' |
+    'Tab' | 'Enter' | 'Escape' | 'Space' | 'Backspace' | 'Delete' | 'CapsLock' |
+    'Up' | 'Down' | 'Left' | 'Right' | 'Home' | 'End' | 'PageUp' | 'PageDown' |
+    Modifier;
+
+export type Modifier = 'Shift' | 'Control' | 'Alt' | 'Meta';
+
+export type MediaKey = 'PlayPause' | 'FastForward' | 'Rewind' | 'VolumeUp' | 'VolumeDown' | 'Mute';
+
 export type RemoteControlEvent = {
     DPad?: 'Up' | 'Down' | 'Left' | 'Right' | 'Enter' | 'Exit';
     Text?: string;
-    Keyboard?: 'Backspace';
+    Keyboard?: Key;
+    Chord?: {
+        modifiers: Modifier[],
+        key: Key
+    };
+    Media?: MediaKey;
     MouseMove?: {
         dx: number,
         dy: number
     };
+    MousePosition?: {
+        x: number,
+        y: number
+    };
     MouseDown?: 'LeftButton' | 'RightButton' | 'MiddleButton';
     MouseUp?: 'LeftButton' | 'RightButton' | 'MiddleButton';
     MouseClick?: 'LeftButton' | 'RightButton' | 'MiddleButton';
@@ -28,7 +53,52 @@ export type RemoteControlEvent = {
         dx: number,
         dy: number
     };
-    Action?: 'Home' | 'AltTab'
+    Action?: 'Home' | 'AltTab';
+    Macro?: string;
+    Gamepad?: GamepadEvent;
+}
+
+export type GamepadButton =
+    'A' | 'B' | 'X' | 'Y' | 'LeftBumper' | 'RightBumper' | 'Back' | 'Start' | 'Guide' |
+    'LeftStick' | 'RightStick' | 'DPadUp' | 'DPadDown' | 'DPadLeft' | 'DPadRight';
+
+export type Side = 'Left' | 'Right';
+
+export type GamepadEvent = {
+    Button?: {
+        button: GamepadButton,
+        pressed: boolean
+    };
+    // From -1 to 1, positive y is down
+    Stick?: {
+        stick: Side,
+        x: number,
+        y: number
+    };
+    // From 0 to 1
+    Trigger?: {
+        trigger: Side,
+        value: number
+    };
+}
+
+export interface MacroInfo {
+    name: string;
+    icon?: string;
+}
+
+export type RemoteMessage = {
+    Macros?: MacroInfo[];
+    MacroProgress?: {
+        name: string,
+        step: number,
+        steps: number
+    };
+    MacroDone?: {
+        name: string
+    };
+    Player?: number;
+    InputAvailable?: boolean;
 }
 
 // A thin wrapper around a WebSocket, to send events
@@ -138,10 +208,50 @@ export const connectWs = async (
         onAccept();
     });
     
+    ws.addEventListener('message', (event) => {
+        onMessage(JSON.parse(event.data) as RemoteMessage);
+    });
+    
     ws.addEventListener('close', () => {
         connection.set(null);
+        macroProgress.set({});
         onClose();
     });
 }
 
-export const connection: Writable<null | EventSocket> = writable(null);
\ No newline at end of file
+const onMessage = (message: RemoteMessage) => {
+    if (message.Macros) {
+        macros.set(message.Macros);
+    }
+    if (message.MacroProgress) {
+        const { name, step, steps } = message.MacroProgress;
+        macroProgress.update((progress) => ({ ...progress, [name]: { step, steps } }));
+    }
+    if (message.Player) {
+        player.set(message.Player);
+    }
+    if (message.InputAvailable !== undefined) {
+        inputAvailable.set(message.InputAvailable);
+    }
+    if (message.MacroDone) {
+        const { name } = message.MacroDone;
+        macroProgress.update((progress) => {
+            delete progress[name];
+            return progress;
+        });
+    }
+}
+
+export const connection: Writable<null | EventSocket> = writable(null);
+
+export const macros: Writable<MacroInfo[]> = writable([]);
+
+// Which player this remote's gamepad is, counting from 1
+export const player: Writable<number | null> = writable(null);
+
+// False while the server's input backend is down and restarting, when
+// anything sent is dropped
+export const inputAvailable: Writable<boolean> = writable(true);
+
+// Macros that are currently running, by name
+export const macroProgress: Writable<{[name: string]: { step: number, steps: number }}> = writable({});
\ No newline at end of file
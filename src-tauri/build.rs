@@ -1,3 +1,19 @@
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+// The config types are compiled into the build script as well, so the schema
+// (and the frontend's types) are regenerated whenever they change
+#[allow(dead_code)]
+#[path = "src/config/mod.rs"]
+mod config;
+
+const SCHEMA_PATH: &str = "schema/config.schema.json";
+const TS_PATH: &str = "../src/lib/config.gen.ts";
+/// Set to also write the generated files into the source tree (at `SCHEMA_PATH`
+/// and `TS_PATH`), see `npm run generate:config`. Otherwise they only go to
+/// `OUT_DIR`, so building doesn't need a writable checkout.
+const WRITE_GENERATED_VAR: &str = "MACROPINNA_WRITE_GENERATED";
+
 fn main() {
     // TODO this doesn't do anything because if remote-static is gitignore'd,
     // then it doesn't get tracked for changes even if explicitly mentioned.
@@ -5,5 +21,155 @@ fn main() {
     // and manually trigger a recompile from the Tauri project
     // println!("cargo:rerun-if-changed=remote-static");
 
+    println!("cargo:rerun-if-changed=src/config");
+    println!("cargo:rerun-if-env-changed={}", WRITE_GENERATED_VAR);
+    generate_config_types();
+
     tauri_build::build()
 }
+
+fn generate_config_types() {
+    let schema = schemars::schema_for!(config::Config);
+    let schema = serde_json::to_value(&schema).unwrap();
+
+    let schema_json = format!("{}\n", serde_json::to_string_pretty(&schema).unwrap());
+    let ts = typescript(&schema);
+
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    write_if_changed(&out_dir.join("config.schema.json"), &schema_json);
+    write_if_changed(&out_dir.join("config.gen.ts"), &ts);
+
+    if std::env::var_os(WRITE_GENERATED_VAR).is_some() {
+        write_if_changed(Path::new(SCHEMA_PATH), &schema_json);
+        write_if_changed(Path::new(TS_PATH), &ts);
+    }
+}
+
+// Only touch the files when they actually change, otherwise every build would
+// kick off the frontend's file watcher
+fn write_if_changed(path: &Path, contents: &str) {
+    if std::fs::read_to_string(path).is_ok_and(|old| old == contents) {
+        return;
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).unwrap();
+    }
+    std::fs::write(path, contents).unwrap();
+}
+
+/// Translate the config's JSON Schema into TypeScript declarations. Only
+/// handles the parts of JSON Schema that schemars actually generates for the
+/// config types.
+fn typescript(schema: &Value) -> String {
+    let mut out = String::from(
+        "// Generated from the Rust config types by src-tauri/build.rs, do not edit.\n"
+    );
+
+    out += &format!("\nexport type Config = {};\n", ts_type(schema, 0));
+
+    if let Some(Value::Object(definitions)) = schema.get("definitions") {
+        for (name, definition) in definitions {
+            out += &format!("\nexport type {} = {};\n", name, ts_type(definition, 0));
+        }
+    }
+
+    out
+}
+
+fn ts_type(schema: &Value, indent: usize) -> String {
+    let schema = match schema {
+        Value::Bool(true) => return "any".to_string(),
+        Value::Bool(false) => return "never".to_string(),
+        Value::Object(schema) => schema,
+        _ => return "unknown".to_string()
+    };
+
+    if let Some(Value::String(reference)) = schema.get("$ref") {
+        return reference.rsplit('/').next().unwrap().to_string();
+    }
+
+    if let Some(value) = schema.get("const") {
+        return value.to_string();
+    }
+
+    if let Some(Value::Array(values)) = schema.get("enum") {
+        return union(values.iter().map(|value| value.to_string()));
+    }
+
+    for key in ["oneOf", "anyOf"] {
+        if let Some(Value::Array(schemas)) = schema.get(key) {
+            return union(schemas.iter().map(|schema| ts_type(schema, indent)));
+        }
+    }
+
+    if let Some(Value::Array(schemas)) = schema.get("allOf") {
+        if schemas.len() == 1 {
+            return ts_type(&schemas[0], indent);
+        }
+    }
+
+    match schema.get("type") {
+        Some(Value::Array(types)) => union(types.iter().map(|ty| {
+            let mut schema = schema.clone();
+            schema.insert("type".to_string(), ty.clone());
+            ts_type(&Value::Object(schema), indent)
+        })),
+        Some(Value::String(ty)) => match ty.as_str() {
+            "string" => "string".to_string(),
+            "number" | "integer" => "number".to_string(),
+            "boolean" => "boolean".to_string(),
+            "null" => "null".to_string(),
+            "array" => {
+                let items = schema.get("items").map(|items| ts_type(items, indent));
+                match items {
+                    Some(items) if items.contains('|') => format!("({})[]", items),
+                    Some(items) => format!("{}[]", items),
+                    None => "unknown[]".to_string()
+                }
+            }
+            "object" => ts_object(schema, indent),
+            _ => "unknown".to_string()
+        },
+        _ => "unknown".to_string()
+    }
+}
+
+fn ts_object(schema: &serde_json::Map<String, Value>, indent: usize) -> String {
+    let properties = match schema.get("properties") {
+        Some(Value::Object(properties)) => properties,
+        _ => {
+            return match schema.get("additionalProperties") {
+                Some(values) => format!("{{ [key: string]: {} }}", ts_type(values, indent)),
+                None => "{ [key: string]: unknown }".to_string()
+            };
+        }
+    };
+
+    let required = match schema.get("required") {
+        Some(Value::Array(required)) => required.clone(),
+        _ => vec![]
+    };
+
+    let pad = "    ".repeat(indent + 1);
+    let mut out = String::from("{\n");
+    for (name, property) in properties {
+        let optional = if required.contains(&Value::String(name.clone())) { "" } else { "?" };
+        out += &format!("{}{}{}: {};\n", pad, name, optional, ts_type(property, indent + 1));
+    }
+    out += &format!("{}}}", "    ".repeat(indent));
+
+    out
+}
+
+/// Join types with `|`, dropping repeats but otherwise keeping their order.
+fn union<I: Iterator<Item = String>>(types: I) -> String {
+    let mut unique: Vec<String> = vec![];
+    for ty in types {
+        if !unique.contains(&ty) {
+            unique.push(ty);
+        }
+    }
+
+    unique.join(" | ")
+}
@@ -0,0 +1,32 @@
+//! Messages from the remote server to the app.
+//!
+//! The app listens on a localhost port (`RemoteServer::port_app`) for these,
+//! one JSON object per line. It's a plain TCP socket rather than HTTP because
+//! the sender is the remote's input worker, which is a plain thread.
+
+use serde::{Serialize, Deserialize};
+use std::{io::Write, net::TcpStream};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum AppMessage {
+    /// Go back to the home screen
+    Home,
+    /// Launch (or raise) a launcher, by name
+    Launch {
+        name: String
+    },
+    /// Send the launcher in the foreground to the remote server's internal
+    /// port, which it missed if it started after the app
+    GetForeground
+}
+
+/// Send a message to the app listening on `port`.
+pub fn send(port: u16, message: &AppMessage) -> anyhow::Result<()> {
+    let mut stream = TcpStream::connect(("127.0.0.1", port))?;
+
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    stream.write_all(&line)?;
+
+    Ok(())
+}
@@ -0,0 +1,33 @@
+//! Messages over remotes' WebSocket connection. Remotes send
+//! `RemoteControlEvent`s, and get `RemoteMessage`s back.
+
+use serde::{Serialize, Deserialize};
+
+pub use crate::config::input::*;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum RemoteMessage {
+    /// The macros to show as custom buttons. Sent on connecting, and whenever
+    /// they change.
+    Macros(Vec<MacroInfo>),
+    /// A macro started its `step`th step (counting from 0).
+    MacroProgress {
+        name: String,
+        step: usize,
+        steps: usize
+    },
+    MacroDone {
+        name: String
+    },
+    /// Which player the remote's gamepad is, counting from 1.
+    Player(usize),
+    /// Whether input can be played. It can't while the input backend is
+    /// restarting after an error. Sent on connecting, and whenever it changes.
+    InputAvailable(bool),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MacroInfo {
+    pub name: String,
+    pub icon: Option<String>
+}
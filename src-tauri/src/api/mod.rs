@@ -1 +1,3 @@
+pub mod app;
+pub mod input;
 pub mod remote;
\ No newline at end of file
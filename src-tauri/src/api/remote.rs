@@ -15,7 +15,28 @@ pub struct AccessInfo {
 pub struct ActiveInfo {
     uuid: Uuid,
     name: String,
-    agent: Agent
+    agent: Agent,
+    input: InputStats,
+    /// Average round trip time of pings over the WebSocket, in ms
+    rtt_ms: Option<f64>
+}
+
+/// Counts of input events from a remote, since it connected.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct InputStats {
+    /// Events received over the socket
+    pub received: u64,
+    /// Events played by the input worker
+    pub played: u64,
+    /// Moves and scrolls merged into one still waiting in the queue
+    pub coalesced: u64,
+    /// Events dropped because the queue was full, or because input was
+    /// unavailable
+    pub dropped: u64,
+    /// Events dropped because they waited longer than the latency budget
+    pub stale: u64,
+    /// Average time from receiving an event to injecting it, in ms
+    pub latency_ms: Option<f64>
 }
 
 /// Types of user agents (guessed based on User-Agent header - not a security thing).
@@ -25,4 +46,11 @@ pub enum Agent {
     IPhone,
     Desktop,
     Unknown
-}
\ No newline at end of file
+}
+
+/// The launcher the app last brought to the foreground, if any. Sent by the
+/// app so the remote can use that launcher's bindings.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Foreground {
+    pub launcher: Option<String>
+}
@@ -0,0 +1,102 @@
+//! Listener for messages from the remote server (see `shared::api::app`).
+
+use crate::{config_listener::ConfigManager, launcher::{self, LauncherManager}};
+use shared::api::app::AppMessage;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager, Wry};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    net::TcpListener
+};
+
+#[derive(Clone, Serialize)]
+enum AppEvent {
+    Home
+}
+
+impl AppEvent {
+    fn channel() -> &'static str {
+        "app"
+    }
+}
+
+pub async fn run(app_handle: AppHandle<Wry>, port: u16) {
+    let listener = match TcpListener::bind(("127.0.0.1", port)).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            log::error!("Could not listen for the remote server on port {}: {}", port, err);
+            return;
+        }
+    };
+
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                log::error!("Error while accepting connection from the remote server: {}", err);
+                continue;
+            }
+        };
+
+        let app_handle = app_handle.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stream).lines();
+
+            while let Ok(Some(line)) = lines.next_line().await {
+                let message = match serde_json::from_str::<AppMessage>(&line) {
+                    Ok(message) => message,
+                    Err(err) => {
+                        log::error!("Error while deserializing message \"{}\": {}", line, err);
+                        continue;
+                    }
+                };
+
+                // Handling talks to the window manager (D-Bus, X11, Wayland),
+                // which blocks. Waiting for it keeps messages in order
+                let app_handle = app_handle.clone();
+                if let Err(err) = tokio::task::spawn_blocking(move || handle(&app_handle, message)).await {
+                    log::error!("Error while handling message: {}", err);
+                }
+            }
+        });
+    }
+}
+
+fn handle(app_handle: &AppHandle<Wry>, message: AppMessage) {
+    match message {
+        AppMessage::Home => {
+            let launcher_manager = app_handle.state::<LauncherManager>();
+            let config_manager = app_handle.state::<ConfigManager>();
+            launcher_manager.home(&config_manager);
+
+            match app_handle.get_window("main") {
+                Some(window) => {
+                    let res = window.unminimize()
+                        .and_then(|()| window.show())
+                        .and_then(|()| window.set_focus());
+
+                    if let Err(err) = res {
+                        log::error!("Could not raise the app window: {}", err);
+                    }
+                }
+                None => log::error!("Could not find the app window")
+            }
+
+            if let Err(err) = app_handle.emit_all(AppEvent::channel(), AppEvent::Home) {
+                log::error!("{}", err);
+            }
+        }
+        AppMessage::Launch { name } => {
+            let launcher_manager = app_handle.state::<LauncherManager>();
+            let config_manager = app_handle.state::<ConfigManager>();
+
+            if let Err(err) = launcher::start(&launcher_manager, &config_manager, name) {
+                log::error!("{}", err);
+            }
+        }
+        AppMessage::GetForeground => {
+            app_handle.state::<LauncherManager>().send_foreground();
+        }
+    }
+}
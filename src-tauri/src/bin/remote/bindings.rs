@@ -0,0 +1,142 @@
+//! The remote's button bindings, macros and pointer settings, kept up to date
+//! with the config and with the launcher the app has in the foreground.
+
+use shared::{
+    api::input::MacroInfo,
+    config::{
+        self,
+        Config,
+        Pointer,
+        bindings::{Binding, Bindings},
+        input::{Action, DPadDirection},
+        macros::{Macro, MacroStep},
+        profile::Profiles
+    },
+    util::project_dirs
+};
+
+use notify::RecommendedWatcher;
+use std::sync::{Arc, RwLock};
+use tokio::sync::watch;
+
+pub struct BindingsState {
+    bindings: RwLock<Bindings>,
+    macros: RwLock<Vec<Macro>>,
+    shell: RwLock<String>,
+    pointer: RwLock<Pointer>,
+    foreground: RwLock<Option<String>>,
+    /// What connected remotes should show as custom buttons
+    macro_list: watch::Sender<Vec<MacroInfo>>,
+    /// The ports the server was started on. Changing them (including by
+    /// switching profiles) needs a restart
+    ports: (u16, u16)
+}
+
+impl BindingsState {
+    pub fn new(config: &Config) -> Self {
+        let state = BindingsState {
+            bindings: RwLock::new(Bindings::default()),
+            macros: RwLock::new(vec![]),
+            shell: RwLock::new(String::new()),
+            pointer: RwLock::new(Pointer::default()),
+            foreground: RwLock::new(None),
+            macro_list: watch::channel(vec![]).0,
+            ports: (config.remote_server.port, config.remote_server.port_internal)
+        };
+        state.set_config(config);
+
+        state
+    }
+
+    pub fn set_config(&self, config: &Config) {
+        let ports = (config.remote_server.port, config.remote_server.port_internal);
+        if ports != self.ports {
+            log::warn!(
+                "The config's remote server ports changed to {} and {}, restart the remote server to use them",
+                ports.0,
+                ports.1
+            );
+        }
+
+        *self.bindings.write().unwrap() = config.bindings.clone().unwrap_or_default();
+        *self.shell.write().unwrap() = config.shell.clone().unwrap_or_else(|| "sh".to_string());
+        *self.pointer.write().unwrap() = config.remote_server.pointer.unwrap_or_default();
+
+        let macros = config.macros.clone().unwrap_or_default();
+        let macro_list = macros
+            .iter()
+            .map(|m| MacroInfo { name: m.name.clone(), icon: m.icon.clone() })
+            .collect::<Vec<_>>();
+        *self.macros.write().unwrap() = macros;
+
+        self.macro_list.send_if_modified(|list| {
+            let modified = *list != macro_list;
+            *list = macro_list;
+            modified
+        });
+    }
+
+    pub fn set_foreground(&self, launcher: Option<String>) {
+        *self.foreground.write().unwrap() = launcher;
+    }
+
+    pub fn dpad(&self, direction: DPadDirection) -> Binding {
+        let foreground = self.foreground.read().unwrap();
+        self.bindings.read().unwrap().dpad(foreground.as_deref(), direction)
+    }
+
+    pub fn action(&self, action: Action) -> Binding {
+        let foreground = self.foreground.read().unwrap();
+        self.bindings.read().unwrap().action(foreground.as_deref(), action)
+    }
+
+    pub fn macro_steps(&self, name: &str) -> Option<Vec<MacroStep>> {
+        self.macros
+            .read()
+            .unwrap()
+            .iter()
+            .find(|m| m.name == name)
+            .map(|m| m.steps.clone())
+    }
+
+    pub fn subscribe_macros(&self) -> watch::Receiver<Vec<MacroInfo>> {
+        self.macro_list.subscribe()
+    }
+
+    pub fn shell(&self) -> String {
+        self.shell.read().unwrap().clone()
+    }
+
+    pub fn pointer(&self) -> Pointer {
+        *self.pointer.read().unwrap()
+    }
+
+    /// Reload the bindings whenever the active config changes (including
+    /// switching profiles).
+    pub fn watch(self: &Arc<Self>) -> notify::Result<RecommendedWatcher> {
+        use notify::{Event, RecursiveMode, Watcher};
+
+        let base_path = project_dirs::config_dir();
+        let profiles = Profiles::new(base_path);
+        let state = self.clone();
+
+        let mut watcher = notify::recommended_watcher(
+            move |res: notify::Result<Event>| {
+                if let Ok(event) = res {
+                    if event.kind.is_modify() {
+                        if let Some(config) = config::load_from_path(profiles.active_config_path()) {
+                            log::info!("Reloading bindings");
+                            state.set_config(&config);
+                        }
+                    }
+                }
+            }
+        )?;
+
+        // Watching the directory, for the same reasons as the app's config
+        // watcher
+        watcher.watch(base_path, RecursiveMode::Recursive)?;
+
+        Ok(watcher)
+    }
+}
@@ -0,0 +1,173 @@
+//! Virtual gamepads through `uinput`, one per remote. They identify as wired
+//! Xbox 360 controllers, which games and Steam already know how to map.
+
+use evdev::{
+    AbsInfo, AbsoluteAxisType, AttributeSet, BusType, EventType, InputEvent, InputId,
+    UinputAbsSetup,
+    uinput::{VirtualDevice, VirtualDeviceBuilder}
+};
+use shared::api::input::{GamepadButton, GamepadEvent, Side};
+use std::collections::{BTreeMap, BTreeSet, btree_map::Entry};
+
+const DEVICE_NAME: &str = "Microsoft X-Box 360 pad";
+const VENDOR: u16 = 0x045e;
+const PRODUCT: u16 = 0x028e;
+const VERSION: u16 = 0x0110;
+
+const STICK_MAX: i32 = 32767;
+const TRIGGER_MAX: i32 = 255;
+
+/// Gamepads by slot. Slots are handed out by the server, so that the first
+/// remote to connect is player 1 and so on.
+#[derive(Default)]
+pub struct Gamepads {
+    slots: BTreeMap<usize, Gamepad>
+}
+
+struct Gamepad {
+    device: VirtualDevice,
+    /// Pressed D-pad buttons, since the D-pad is a pair of axes
+    dpad: BTreeSet<GamepadButton>
+}
+
+impl Gamepads {
+    pub fn play(&mut self, slot: usize, event: GamepadEvent) -> anyhow::Result<()> {
+        let gamepad = match self.slots.entry(slot) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                log::info!("Creating gamepad for player {}", slot + 1);
+                entry.insert(Gamepad::new()?)
+            }
+        };
+
+        gamepad.play(event)
+    }
+
+    /// Unplug a slot's gamepad, if it has one.
+    pub fn release(&mut self, slot: usize) {
+        if self.slots.remove(&slot).is_some() {
+            log::info!("Removed gamepad for player {}", slot + 1);
+        }
+    }
+}
+
+impl Gamepad {
+    fn new() -> anyhow::Result<Self> {
+        let buttons = AttributeSet::from_iter([
+            GamepadButton::A,
+            GamepadButton::B,
+            GamepadButton::X,
+            GamepadButton::Y,
+            GamepadButton::LeftBumper,
+            GamepadButton::RightBumper,
+            GamepadButton::Back,
+            GamepadButton::Start,
+            GamepadButton::Guide,
+            GamepadButton::LeftStick,
+            GamepadButton::RightStick
+        ].into_iter().filter_map(evdev_button));
+
+        let stick = AbsInfo::new(0, -STICK_MAX - 1, STICK_MAX, 16, 128, 0);
+        let trigger = AbsInfo::new(0, 0, TRIGGER_MAX, 0, 0, 0);
+        let hat = AbsInfo::new(0, -1, 1, 0, 0, 0);
+
+        let mut builder = VirtualDeviceBuilder::new()?
+            .name(DEVICE_NAME)
+            .input_id(InputId::new(BusType::BUS_USB, VENDOR, PRODUCT, VERSION))
+            .with_keys(&buttons)?;
+        for (axis, info) in [
+            (AbsoluteAxisType::ABS_X, stick),
+            (AbsoluteAxisType::ABS_Y, stick),
+            (AbsoluteAxisType::ABS_RX, stick),
+            (AbsoluteAxisType::ABS_RY, stick),
+            (AbsoluteAxisType::ABS_Z, trigger),
+            (AbsoluteAxisType::ABS_RZ, trigger),
+            (AbsoluteAxisType::ABS_HAT0X, hat),
+            (AbsoluteAxisType::ABS_HAT0Y, hat)
+        ] {
+            builder = builder.with_absolute_axis(&UinputAbsSetup::new(axis, info))?;
+        }
+
+        Ok(Gamepad {
+            device: builder.build()?,
+            dpad: BTreeSet::new()
+        })
+    }
+
+    fn play(&mut self, event: GamepadEvent) -> anyhow::Result<()> {
+        let events = match event {
+            GamepadEvent::Button { button, pressed } => match evdev_button(button) {
+                Some(key) => vec![InputEvent::new(EventType::KEY, key.code(), pressed as i32)],
+                None => {
+                    if pressed {
+                        self.dpad.insert(button);
+                    }
+                    else {
+                        self.dpad.remove(&button);
+                    }
+
+                    let hat = |negative, positive| {
+                        self.dpad.contains(&positive) as i32 - self.dpad.contains(&negative) as i32
+                    };
+                    vec![
+                        abs(AbsoluteAxisType::ABS_HAT0X, hat(GamepadButton::DPadLeft, GamepadButton::DPadRight)),
+                        abs(AbsoluteAxisType::ABS_HAT0Y, hat(GamepadButton::DPadUp, GamepadButton::DPadDown))
+                    ]
+                }
+            },
+            GamepadEvent::Stick { stick, x, y } => {
+                let (axis_x, axis_y) = match stick {
+                    Side::Left => (AbsoluteAxisType::ABS_X, AbsoluteAxisType::ABS_Y),
+                    Side::Right => (AbsoluteAxisType::ABS_RX, AbsoluteAxisType::ABS_RY)
+                };
+
+                vec![abs(axis_x, scale(x, -1.0, STICK_MAX)), abs(axis_y, scale(y, -1.0, STICK_MAX))]
+            }
+            GamepadEvent::Trigger { trigger, value } => {
+                let axis = match trigger {
+                    Side::Left => AbsoluteAxisType::ABS_Z,
+                    Side::Right => AbsoluteAxisType::ABS_RZ
+                };
+
+                vec![abs(axis, scale(value, 0.0, TRIGGER_MAX))]
+            }
+        };
+
+        Ok(self.device.emit(&events)?)
+    }
+}
+
+fn abs(axis: AbsoluteAxisType, value: i32) -> InputEvent {
+    InputEvent::new(EventType::ABSOLUTE, axis.0, value)
+}
+
+/// Scale `value` from `min..1` to `min * max..max`, clamping anything outside
+/// (including NaN, which ends up at 0).
+fn scale(value: f64, min: f64, max: i32) -> i32 {
+    let value = if value.is_nan() { 0.0 } else { value.clamp(min, 1.0) };
+    (value * max as f64).round() as i32
+}
+
+/// Buttons are keys, except for the D-pad, which is a pair of axes.
+fn evdev_button(button: GamepadButton) -> Option<evdev::Key> {
+    use evdev::Key as Ev;
+    use GamepadButton::*;
+
+    // Same layout as the kernel's xpad driver
+    let key = match button {
+        A => Ev::BTN_SOUTH,
+        B => Ev::BTN_EAST,
+        X => Ev::BTN_NORTH,
+        Y => Ev::BTN_WEST,
+        LeftBumper => Ev::BTN_TL,
+        RightBumper => Ev::BTN_TR,
+        Back => Ev::BTN_SELECT,
+        Start => Ev::BTN_START,
+        Guide => Ev::BTN_MODE,
+        LeftStick => Ev::BTN_THUMBL,
+        RightStick => Ev::BTN_THUMBR,
+        DPadUp | DPadDown | DPadLeft | DPadRight => return None
+    };
+
+    Some(key)
+}
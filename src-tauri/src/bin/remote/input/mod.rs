@@ -0,0 +1,590 @@
+//! Handling for user input events.
+//! 
+//! Input is injected by a `Backend`: `tfc` on X11 (and other desktops), or
+//! virtual devices through `uinput` on Linux, which works under Wayland and
+//! without any display server. Since backends aren't thread-safe (they can't
+//! be, they need access to file handles), the backend has to get created in
+//! its own worker thread and have events wired to it, through a `Queue`.
+//! 
+//! If the worker can't open its backend, keeps failing, or panics, it gets
+//! restarted with a backoff, and remotes are told input is unavailable until
+//! it's back.
+
+#[cfg(target_os = "linux")]
+mod gamepad;
+mod queue;
+mod tfc;
+#[cfg(target_os = "linux")]
+mod uinput;
+
+pub use queue::Stats;
+
+use crate::bindings::BindingsState;
+use queue::Queue;
+
+use std::{
+    sync::Arc,
+    thread::JoinHandle,
+    time::{Duration, Instant}
+};
+use tokio::sync::{mpsc::UnboundedSender, watch};
+
+use shared::{
+    api::{
+        app::{self, AppMessage},
+        input::*
+    },
+    config::{
+        bindings::{Binding, KeyChord},
+        InputBackend,
+        macros::{self, MacroStep}
+    },
+    util::numeric::try_f64_to_i32
+};
+
+/// How long to wait before the first restart of a failed worker. Each
+/// restart in a row waits twice as long, up to `MAX_BACKOFF`.
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// A worker that's been up this long is considered healthy again, and the
+/// backoff starts over.
+const HEALTHY_AFTER: Duration = Duration::from_secs(60);
+
+/// Backend errors in a row before the backend is considered broken, and the
+/// worker restarts with a fresh one.
+const MAX_ERRORS: u32 = 10;
+
+pub struct Context {
+    queue: Arc<Queue>,
+    bindings: Arc<BindingsState>,
+    /// Whether a worker is up and playing events
+    available: Arc<watch::Sender<bool>>,
+    _handle: JoinHandle<()>
+}
+
+enum Job {
+    /// Play an event from the remote in `slot`, reporting progress on macros
+    /// to `reply`.
+    Play {
+        event: RemoteControlEvent,
+        slot: usize,
+        reply: Option<UnboundedSender<RemoteMessage>>,
+        received: Instant,
+        stats: Arc<Stats>
+    },
+    /// The remote in `slot` is gone
+    Release {
+        slot: usize
+    }
+}
+
+impl Job {
+    fn slot(&self) -> usize {
+        match self {
+            Job::Play { slot, .. } | Job::Release { slot } => *slot
+        }
+    }
+}
+
+/// Owns the backend, on the worker thread.
+struct Worker {
+    backend: Box<dyn Backend>,
+    #[cfg(target_os = "linux")]
+    gamepads: gamepad::Gamepads,
+    bindings: Arc<BindingsState>,
+    port_app: u16,
+    /// What's left over from rounding relative pointer movement, so slow
+    /// movement still adds up to whole pixels
+    pointer_remainder: (f64, f64)
+}
+
+/// Any key a backend can press.
+#[derive(Clone, Copy, Debug)]
+pub enum AnyKey {
+    Key(Key),
+    Media(MediaKey)
+}
+
+impl From<Key> for AnyKey {
+    fn from(key: Key) -> Self {
+        AnyKey::Key(key)
+    }
+}
+
+impl From<MediaKey> for AnyKey {
+    fn from(key: MediaKey) -> Self {
+        AnyKey::Media(key)
+    }
+}
+
+impl From<Modifier> for AnyKey {
+    fn from(modifier: Modifier) -> Self {
+        AnyKey::Key(match modifier {
+            Modifier::Shift => Key::Shift,
+            Modifier::Control => Key::Control,
+            Modifier::Alt => Key::Alt,
+            Modifier::Meta => Key::Meta,
+        })
+    }
+}
+
+pub trait Backend {
+    /// For logging.
+    fn name(&self) -> &'static str;
+
+    fn key_down(&mut self, key: AnyKey) -> anyhow::Result<()>;
+
+    fn key_up(&mut self, key: AnyKey) -> anyhow::Result<()>;
+
+    fn key_click(&mut self, key: AnyKey) -> anyhow::Result<()> {
+        self.key_down(key)?;
+        self.key_up(key)
+    }
+
+    /// Type some text, with `\n` as Enter.
+    fn text(&mut self, text: &str) -> anyhow::Result<()>;
+
+    fn mouse_move(&mut self, dx: i32, dy: i32) -> anyhow::Result<()>;
+
+    /// Move to a point on the display, from (0, 0) at the top left to (1, 1)
+    /// at the bottom right. `x` and `y` are already clamped.
+    fn mouse_position(&mut self, x: f64, y: f64) -> anyhow::Result<()>;
+
+    fn mouse_down(&mut self, button: MouseButton) -> anyhow::Result<()>;
+
+    fn mouse_up(&mut self, button: MouseButton) -> anyhow::Result<()>;
+
+    fn mouse_click(&mut self, button: MouseButton) -> anyhow::Result<()> {
+        self.mouse_down(button)?;
+        self.mouse_up(button)
+    }
+
+    /// Positive `dy` scrolls down.
+    fn mouse_scroll(&mut self, dx: i32, dy: i32) -> anyhow::Result<()>;
+}
+
+/// Open the configured backend. `Auto` goes with `uinput` on Wayland or
+/// without a display server, and `tfc` otherwise, trying the other one if the
+/// first choice fails.
+fn open_backend(choice: InputBackend) -> anyhow::Result<Box<dyn Backend>> {
+    fn open_tfc() -> anyhow::Result<Box<dyn Backend>> {
+        Ok(Box::new(tfc::Tfc::new()?))
+    }
+
+    #[cfg(target_os = "linux")]
+    fn open_uinput() -> anyhow::Result<Box<dyn Backend>> {
+        Ok(Box::new(uinput::Uinput::new()?))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn open_uinput() -> anyhow::Result<Box<dyn Backend>> {
+        anyhow::bail!("uinput is only available on Linux")
+    }
+
+    match choice {
+        InputBackend::Tfc => open_tfc(),
+        InputBackend::Uinput => open_uinput(),
+        InputBackend::Auto => {
+            let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+            let x11 = std::env::var_os("DISPLAY").is_some();
+
+            let (first, second): (fn() -> _, fn() -> _) =
+                if cfg!(target_os = "linux") && (wayland || !x11) {
+                    (open_uinput, open_tfc)
+                }
+                else {
+                    (open_tfc, open_uinput)
+                };
+
+            first().or_else(|err| {
+                log::warn!("Could not open the preferred input backend ({}), trying another", err);
+                second()
+            })
+        }
+    }
+}
+
+/// Click `key` with `modifiers` held down. Modifiers are released in reverse
+/// order, and released even if clicking the key fails, so they don't get stuck.
+fn chord(
+    backend: &mut dyn Backend,
+    modifiers: &[Modifier],
+    key: Key
+) -> anyhow::Result<()> {
+    let mut held = vec![];
+    let mut result = Ok(());
+
+    for &modifier in modifiers {
+        result = backend.key_down(modifier.into());
+        if result.is_err() {
+            break;
+        }
+        held.push(modifier);
+    }
+
+    if result.is_ok() {
+        result = backend.key_click(key.into());
+    }
+
+    for &modifier in held.iter().rev() {
+        let released = backend.key_up(modifier.into());
+        if result.is_ok() {
+            result = released;
+        }
+    }
+
+    result
+}
+
+impl Worker {
+    fn play(
+        &mut self,
+        event: RemoteControlEvent,
+        slot: usize,
+        reply: Option<&UnboundedSender<RemoteMessage>>
+    ) -> anyhow::Result<()> {
+        use RemoteControlEvent::*;
+
+        let backend = self.backend.as_mut();
+
+        match event {
+            DPad(direction) => {
+                let binding = self.bindings.dpad(direction);
+                self.run_binding(binding)?;
+            }
+            Text(text) => {
+                backend.text(&text)?;
+            }
+            Keyboard(key) => {
+                backend.key_click(key.into())?;
+            }
+            Chord { modifiers, key } => {
+                chord(backend, &modifiers, key)?;
+            }
+            Media(key) => {
+                backend.key_click(key.into())?;
+            }
+            MouseMove { dx, dy } => {
+                let dx = dx + self.pointer_remainder.0;
+                let dy = dy + self.pointer_remainder.1;
+                self.pointer_remainder = (dx - dx.round(), dy - dy.round());
+
+                let dx = try_f64_to_i32(dx);
+                let dy = try_f64_to_i32(dy);
+                
+                match (dx, dy) {
+                    (Some(dx), Some(dy)) => {
+                        backend.mouse_move(dx, dy)?;
+                    },
+                    _ => {
+                        self.pointer_remainder = (0.0, 0.0);
+                    }
+                };
+            }
+            MousePosition { x, y } => {
+                if x.is_finite() && y.is_finite() {
+                    backend.mouse_position(x.clamp(0.0, 1.0), y.clamp(0.0, 1.0))?;
+                }
+            }
+            MouseDown(button) => {
+                backend.mouse_down(button)?;
+            }
+            MouseUp(button) => {
+                backend.mouse_up(button)?;
+            }
+            MouseClick(button) => {
+                backend.mouse_click(button)?;
+            }
+            MouseScroll { dx, dy } => {
+                let dx = try_f64_to_i32(dx);
+                let dy = try_f64_to_i32(dy);
+                
+                match (dx, dy) {
+                    (Some(dx), Some(dy)) => {
+                        backend.mouse_scroll(dx, dy)?;
+                    },
+                    _ => {}
+                };
+            }
+            Action(action) => {
+                let binding = self.bindings.action(action);
+                self.run_binding(binding)?;
+            }
+            Macro(name) => {
+                self.run_macro(&name, slot, reply)?;
+            }
+            Gamepad(event) => {
+                // Gamepads don't go through the backend, so failing them
+                // doesn't mean the backend is broken
+                if let Err(err) = self.play_gamepad(slot, event) {
+                    log::error!("Error while playing gamepad event: {}", err);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Do whatever a D-pad or action button is bound to.
+    fn run_binding(&mut self, binding: Binding) -> anyhow::Result<()> {
+        match binding {
+            Binding::Keys(chords) => {
+                for KeyChord { modifiers, key } in chords {
+                    chord(self.backend.as_mut(), &modifiers.unwrap_or_default(), key)?;
+                }
+            }
+            Binding::Launcher(name) => {
+                if let Err(err) = app::send(self.port_app, &AppMessage::Launch { name: name.clone() }) {
+                    log::error!("Could not tell the app to launch \"{}\": {}", name, err);
+                }
+            }
+            Binding::Command(command) => {
+                let shell = self.bindings.shell();
+                match std::process::Command::new(shell).args(["-c", &command]).spawn() {
+                    Ok(mut child) => {
+                        // Reap it in the background, without holding up input
+                        std::thread::spawn(move || child.wait());
+                    }
+                    Err(err) => log::error!("Could not run \"{}\": {}", command, err)
+                }
+            }
+            Binding::Home => {
+                if let Err(err) = app::send(self.port_app, &AppMessage::Home) {
+                    log::error!("Could not tell the app to go home: {}", err);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Play a macro's steps in order, reporting progress to `reply`. Other
+    /// input waits until the macro is done, delays included. The macro stops
+    /// at the first step the backend fails.
+    fn run_macro(
+        &mut self,
+        name: &str,
+        slot: usize,
+        reply: Option<&UnboundedSender<RemoteMessage>>
+    ) -> anyhow::Result<()> {
+        let steps = match self.bindings.macro_steps(name) {
+            Some(steps) => steps,
+            None => {
+                log::error!("No macro named \"{}\"", name);
+                return Ok(());
+            }
+        };
+
+        let report = |message: RemoteMessage| {
+            if let Some(reply) = reply {
+                let _ = reply.send(message);
+            }
+        };
+
+        log::info!("Running macro \"{}\"", name);
+        let count = steps.len();
+        let mut delay_left = Duration::from_millis(macros::MAX_DELAY_MS);
+        let mut result = Ok(());
+        for (i, step) in steps.into_iter().enumerate() {
+            report(RemoteMessage::MacroProgress { name: name.to_string(), step: i, steps: count });
+
+            result = match step {
+                // Validation keeps macros from running macros, but the config
+                // could have changed since
+                MacroStep::Event(RemoteControlEvent::Macro(_)) => {
+                    log::error!("Macro \"{}\" can't run other macros", name);
+                    Ok(())
+                }
+                MacroStep::Event(event) => self.play(event, slot, None),
+                MacroStep::Run(binding) => self.run_binding(binding),
+                // Same goes for the delay limit
+                MacroStep::Delay { ms } => {
+                    let delay = Duration::from_millis(ms).min(delay_left);
+                    delay_left -= delay;
+                    std::thread::sleep(delay);
+                    Ok(())
+                }
+            };
+            if result.is_err() {
+                break;
+            }
+        }
+
+        report(RemoteMessage::MacroDone { name: name.to_string() });
+        result
+    }
+
+    #[cfg(target_os = "linux")]
+    fn play_gamepad(&mut self, slot: usize, event: GamepadEvent) -> anyhow::Result<()> {
+        self.gamepads.play(slot, event)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn play_gamepad(&mut self, _slot: usize, _event: GamepadEvent) -> anyhow::Result<()> {
+        anyhow::bail!("Gamepads are only available on Linux")
+    }
+
+    #[cfg(target_os = "linux")]
+    fn release(&mut self, slot: usize) {
+        self.gamepads.release(slot);
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn release(&mut self, _slot: usize) {}
+}
+
+impl Context {
+    /// `port_app` is where the app listens for messages, for bindings that
+    /// involve it.
+    pub fn new(backend: InputBackend, port_app: u16, bindings: Arc<BindingsState>) -> Self {
+        let queue = Arc::new(Queue::default());
+        let available = Arc::new(watch::channel(false).0);
+
+        let handle = {
+            let queue = queue.clone();
+            let bindings = bindings.clone();
+            let available = available.clone();
+
+            std::thread::spawn(move || supervise(backend, port_app, bindings, queue, available))
+        };
+
+        Context { queue, bindings, available, _handle: handle }
+    }
+
+    /// Play an event from the remote in `slot`, counting it in `stats`.
+    /// Progress on macros is sent to `reply`, if given. Events are dropped
+    /// while the worker is down, rather than piling up to all play at once
+    /// when it's back.
+    pub fn play_event(
+        &self,
+        event: RemoteControlEvent,
+        slot: usize,
+        reply: Option<UnboundedSender<RemoteMessage>>,
+        stats: Arc<Stats>
+    ) {
+        if !*self.available.borrow() {
+            stats.count_unavailable();
+            return;
+        }
+
+        // The pointer curve goes by each move as the remote sent it, before
+        // moves get merged in the queue. Moves from macros skip it, and go
+        // exactly as far as they say
+        let event = match event {
+            RemoteControlEvent::MouseMove { dx, dy } => {
+                let (dx, dy) = self.bindings.pointer().accelerate(dx, dy);
+                RemoteControlEvent::MouseMove { dx, dy }
+            }
+            event => event
+        };
+
+        self.queue.push(Job::Play { event, slot, reply, received: Instant::now(), stats });
+    }
+
+    /// Let go of whatever the remote in `slot` had, like its gamepad.
+    pub fn release(&self, slot: usize) {
+        self.queue.push(Job::Release { slot });
+    }
+
+    /// Whether input can be played right now, and any changes to that.
+    pub fn subscribe_available(&self) -> watch::Receiver<bool> {
+        self.available.subscribe()
+    }
+}
+
+/// Run workers, one after the other, restarting them with a backoff whenever
+/// one fails or panics. Never returns.
+fn supervise(
+    backend: InputBackend,
+    port_app: u16,
+    bindings: Arc<BindingsState>,
+    queue: Arc<Queue>,
+    available: Arc<watch::Sender<bool>>
+) {
+    let mut backoff = MIN_BACKOFF;
+
+    loop {
+        let started = Instant::now();
+        let worker = {
+            let bindings = bindings.clone();
+            let queue = queue.clone();
+            let available = available.clone();
+
+            std::thread::spawn(move || run_worker(backend, port_app, bindings, &queue, &available))
+        };
+
+        match worker.join() {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => log::error!("Input worker failed: {}", err),
+            Err(_) => log::error!("Input worker panicked")
+        }
+
+        available.send_replace(false);
+        // Anything still queued is from before the failure, and would be late
+        queue.clear();
+
+        if started.elapsed() > HEALTHY_AFTER {
+            backoff = MIN_BACKOFF;
+        }
+        log::info!("Restarting input worker in {}s", backoff.as_secs());
+        std::thread::sleep(backoff);
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Open the backend and play jobs from `queue`, until the backend fails
+/// `MAX_ERRORS` times in a row.
+fn run_worker(
+    backend: InputBackend,
+    port_app: u16,
+    bindings: Arc<BindingsState>,
+    queue: &Queue,
+    available: &watch::Sender<bool>
+) -> anyhow::Result<()> {
+    let backend = open_backend(backend)?;
+    log::info!("Using input backend {}", backend.name());
+
+    let mut worker = Worker {
+        backend,
+        #[cfg(target_os = "linux")]
+        gamepads: gamepad::Gamepads::default(),
+        bindings,
+        port_app,
+        pointer_remainder: (0.0, 0.0)
+    };
+    available.send_replace(true);
+
+    let mut errors = 0;
+    loop {
+        match queue.pop() {
+            Job::Play { event, slot, reply, received, stats } => {
+                if queue::is_relative(&event) && received.elapsed() > queue::LATENCY_BUDGET {
+                    stats.count_stale();
+                    continue;
+                }
+
+                // Macros take as long as their delays, which isn't latency
+                let is_macro = matches!(event, RemoteControlEvent::Macro(_));
+
+                match worker.play(event, slot, reply.as_ref()) {
+                    Ok(()) => {
+                        errors = 0;
+                        stats.count_played();
+                        if !is_macro {
+                            stats.record_latency(received.elapsed());
+                        }
+                    }
+                    Err(err) => {
+                        errors += 1;
+                        if errors >= MAX_ERRORS {
+                            anyhow::bail!("{} errors in a row, the last one: {}", errors, err);
+                        }
+                        log::error!("Error while playing input: {}", err);
+                    }
+                }
+            }
+            Job::Release { slot } => worker.release(slot)
+        }
+    }
+}
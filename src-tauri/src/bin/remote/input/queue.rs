@@ -0,0 +1,344 @@
+//! The queue between remotes and the input worker.
+//!
+//! A touchpad sends far more moves than are worth injecting one at a time, so
+//! consecutive movement from a remote gets merged while it waits: relative
+//! moves and scrolls add up, and pointer positions and stick positions replace
+//! each other. The queue is also capped, and if the worker falls behind anyway,
+//! relative movement that has waited too long is dropped rather than replayed
+//! seconds late. Everything else is only dropped once the queue is so far
+//! behind that something has clearly gone wrong (`MAX_TOTAL_DEPTH`), since a
+//! lost key up or stick release is a lot worse than a late one.
+
+use super::Job;
+
+use shared::{
+    api::{
+        input::{GamepadEvent, RemoteControlEvent},
+        remote::InputStats
+    },
+    util::numeric::moving_average
+};
+use std::{
+    collections::VecDeque,
+    sync::{Condvar, Mutex, atomic::{AtomicU64, Ordering}},
+    time::Duration
+};
+
+/// How many jobs can wait before relative movement starts getting dropped.
+const MAX_DEPTH: usize = 64;
+
+/// How many jobs can wait before everything from remotes gets dropped. People
+/// don't press keys this fast, so getting here takes a stuck backend or a
+/// remote flooding the server, and without a limit either would grow the
+/// queue until the server runs out of memory.
+const MAX_TOTAL_DEPTH: usize = 1024;
+
+/// How long relative movement can wait before it's not worth playing anymore.
+pub const LATENCY_BUDGET: Duration = Duration::from_millis(150);
+
+/// How much each new sample counts toward the average latency.
+const LATENCY_WEIGHT: f64 = 0.1;
+
+#[derive(Default)]
+pub struct Queue {
+    jobs: Mutex<VecDeque<Job>>,
+    ready: Condvar
+}
+
+/// Live counters behind `InputStats`, one set per remote.
+#[derive(Default)]
+pub struct Stats {
+    received: AtomicU64,
+    played: AtomicU64,
+    coalesced: AtomicU64,
+    dropped: AtomicU64,
+    stale: AtomicU64,
+    /// In ms
+    latency: Mutex<Option<f64>>
+}
+
+impl Stats {
+    pub fn snapshot(&self) -> InputStats {
+        InputStats {
+            received: self.received.load(Ordering::Relaxed),
+            played: self.played.load(Ordering::Relaxed),
+            coalesced: self.coalesced.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            stale: self.stale.load(Ordering::Relaxed),
+            latency_ms: *self.latency.lock().unwrap()
+        }
+    }
+
+    pub fn count_played(&self) {
+        self.played.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// How long a played event took from being received to being injected.
+    pub fn record_latency(&self, latency: Duration) {
+        let mut average = self.latency.lock().unwrap();
+        *average = Some(moving_average(*average, latency.as_secs_f64() * 1000.0, LATENCY_WEIGHT));
+    }
+
+    pub fn count_stale(&self) {
+        self.stale.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// An event that came in while there was no worker to play it.
+    pub fn count_unavailable(&self) {
+        self.received.fetch_add(1, Ordering::Relaxed);
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl Queue {
+    pub fn push(&self, job: Job) {
+        { // Lock for jobs
+            let mut jobs = self.jobs.lock().unwrap();
+
+            if let Job::Play { event, slot, stats, received, .. } = &job {
+                stats.received.fetch_add(1, Ordering::Relaxed);
+
+                // Only the remote's latest job can be merged into, so nothing
+                // gets reordered
+                let latest = jobs.iter_mut().rev().find(|queued| queued.slot() == *slot);
+                if let Some(Job::Play { event: queued, received: queued_received, .. }) = latest {
+                    if coalesce(queued, event) {
+                        // The merged job is as fresh as its newest movement,
+                        // it shouldn't go stale for how long the first waited
+                        *queued_received = *received;
+                        stats.coalesced.fetch_add(1, Ordering::Relaxed);
+                        return;
+                    }
+                }
+
+                if (jobs.len() >= MAX_DEPTH && is_relative(event)) || jobs.len() >= MAX_TOTAL_DEPTH {
+                    stats.dropped.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+            }
+
+            jobs.push_back(job);
+        }
+
+        self.ready.notify_one();
+    }
+
+    /// Drop every waiting job.
+    pub fn clear(&self) {
+        self.jobs.lock().unwrap().clear();
+    }
+
+    /// Wait for the next job.
+    pub fn pop(&self) -> Job {
+        let mut jobs = self.jobs.lock().unwrap();
+        loop {
+            match jobs.pop_front() {
+                Some(job) => return job,
+                None => jobs = self.ready.wait(jobs).unwrap()
+            }
+        }
+    }
+}
+
+/// Relative movement, which is fine to lose some of.
+pub fn is_relative(event: &RemoteControlEvent) -> bool {
+    matches!(event, RemoteControlEvent::MouseMove { .. } | RemoteControlEvent::MouseScroll { .. })
+}
+
+/// Merge `event` into `queued` if they're the same kind of movement.
+fn coalesce(queued: &mut RemoteControlEvent, event: &RemoteControlEvent) -> bool {
+    use RemoteControlEvent::*;
+
+    // Positions supersede each other
+    let replace = match (&*queued, event) {
+        (MousePosition { .. }, MousePosition { .. }) => true,
+        (
+            Gamepad(GamepadEvent::Stick { stick, .. }),
+            Gamepad(GamepadEvent::Stick { stick: next, .. })
+        ) => stick == next,
+        _ => false
+    };
+    if replace {
+        *queued = event.clone();
+        return true;
+    }
+
+    // Relative movement adds up
+    match (queued, event) {
+        (MouseMove { dx, dy }, MouseMove { dx: next_dx, dy: next_dy })
+        | (MouseScroll { dx, dy }, MouseScroll { dx: next_dx, dy: next_dy }) => {
+            *dx += next_dx;
+            *dy += next_dy;
+            true
+        }
+        _ => false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::api::input::{MouseButton, Side};
+    use std::{sync::Arc, time::Instant};
+
+    fn play(queue: &Queue, slot: usize, stats: &Arc<Stats>, event: RemoteControlEvent) {
+        queue.push(Job::Play {
+            event,
+            slot,
+            reply: None,
+            received: Instant::now(),
+            stats: stats.clone()
+        });
+    }
+
+    fn events(queue: &Queue) -> Vec<RemoteControlEvent> {
+        queue.jobs
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|job| match job {
+                Job::Play { event, .. } => Some(event.clone()),
+                Job::Release { .. } => None
+            })
+            .collect()
+    }
+
+    fn stick(stick: Side, x: f64) -> RemoteControlEvent {
+        RemoteControlEvent::Gamepad(GamepadEvent::Stick { stick, x, y: 0.0 })
+    }
+
+    const CLICK: RemoteControlEvent = RemoteControlEvent::MouseClick(MouseButton::LeftButton);
+
+    #[test]
+    fn relative_moves_add_up() {
+        let queue = Queue::default();
+        let stats = Arc::new(Stats::default());
+
+        play(&queue, 0, &stats, RemoteControlEvent::MouseMove { dx: 1.0, dy: 2.0 });
+        play(&queue, 0, &stats, RemoteControlEvent::MouseMove { dx: 3.0, dy: -4.0 });
+        play(&queue, 0, &stats, RemoteControlEvent::MouseScroll { dx: 0.0, dy: 1.0 });
+        play(&queue, 0, &stats, RemoteControlEvent::MouseScroll { dx: 0.0, dy: 1.0 });
+
+        let events = events(&queue);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], RemoteControlEvent::MouseMove { dx, dy } if dx == 4.0 && dy == -2.0));
+        assert!(matches!(events[1], RemoteControlEvent::MouseScroll { dx, dy } if dx == 0.0 && dy == 2.0));
+
+        let stats = stats.snapshot();
+        assert_eq!(stats.received, 4);
+        assert_eq!(stats.coalesced, 2);
+    }
+
+    #[test]
+    fn positions_replace_each_other() {
+        let queue = Queue::default();
+        let stats = Arc::new(Stats::default());
+
+        play(&queue, 0, &stats, RemoteControlEvent::MousePosition { x: 0.1, y: 0.2 });
+        play(&queue, 0, &stats, RemoteControlEvent::MousePosition { x: 0.3, y: 0.4 });
+
+        let events = events(&queue);
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0], RemoteControlEvent::MousePosition { x, y } if x == 0.3 && y == 0.4));
+    }
+
+    #[test]
+    fn sticks_replace_only_the_same_stick() {
+        let queue = Queue::default();
+        let stats = Arc::new(Stats::default());
+
+        play(&queue, 0, &stats, stick(Side::Left, 0.1));
+        play(&queue, 0, &stats, stick(Side::Left, 0.2));
+        play(&queue, 0, &stats, stick(Side::Right, 0.3));
+
+        let events = events(&queue);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            events[0],
+            RemoteControlEvent::Gamepad(GamepadEvent::Stick { stick: Side::Left, x, .. }) if x == 0.2
+        ));
+        assert!(matches!(
+            events[1],
+            RemoteControlEvent::Gamepad(GamepadEvent::Stick { stick: Side::Right, .. })
+        ));
+    }
+
+    #[test]
+    fn only_the_latest_job_is_merged_into() {
+        let queue = Queue::default();
+        let stats = Arc::new(Stats::default());
+
+        // A click in between keeps the moves apart
+        play(&queue, 0, &stats, RemoteControlEvent::MouseMove { dx: 1.0, dy: 0.0 });
+        play(&queue, 0, &stats, CLICK);
+        play(&queue, 0, &stats, RemoteControlEvent::MouseMove { dx: 1.0, dy: 0.0 });
+        assert_eq!(events(&queue).len(), 3);
+
+        // Other remotes' jobs don't
+        play(&queue, 1, &stats, RemoteControlEvent::MouseMove { dx: 1.0, dy: 0.0 });
+        play(&queue, 0, &stats, RemoteControlEvent::MouseMove { dx: 1.0, dy: 0.0 });
+
+        let events = events(&queue);
+        assert_eq!(events.len(), 4);
+        assert!(matches!(events[2], RemoteControlEvent::MouseMove { dx, .. } if dx == 2.0));
+    }
+
+    #[test]
+    fn full_queue_drops_only_relative_movement() {
+        let queue = Queue::default();
+        let stats = Arc::new(Stats::default());
+
+        for _ in 0..MAX_DEPTH {
+            play(&queue, 0, &stats, CLICK);
+        }
+
+        play(&queue, 0, &stats, RemoteControlEvent::MouseMove { dx: 1.0, dy: 0.0 });
+        play(&queue, 0, &stats, RemoteControlEvent::MouseScroll { dx: 0.0, dy: 1.0 });
+        assert_eq!(events(&queue).len(), MAX_DEPTH);
+        assert_eq!(stats.snapshot().dropped, 2);
+
+        play(&queue, 0, &stats, CLICK);
+        play(&queue, 0, &stats, stick(Side::Left, 1.0));
+        play(&queue, 0, &stats, RemoteControlEvent::MousePosition { x: 0.5, y: 0.5 });
+        assert_eq!(events(&queue).len(), MAX_DEPTH + 3);
+        assert_eq!(stats.snapshot().dropped, 2);
+    }
+
+    #[test]
+    fn overflowing_queue_drops_everything() {
+        let queue = Queue::default();
+        let stats = Arc::new(Stats::default());
+
+        for _ in 0..MAX_TOTAL_DEPTH {
+            play(&queue, 0, &stats, CLICK);
+        }
+        play(&queue, 0, &stats, CLICK);
+
+        assert_eq!(events(&queue).len(), MAX_TOTAL_DEPTH);
+        assert_eq!(stats.snapshot().dropped, 1);
+
+        // Releasing a remote's gamepad still gets through
+        queue.push(Job::Release { slot: 0 });
+        assert_eq!(queue.jobs.lock().unwrap().len(), MAX_TOTAL_DEPTH + 1);
+    }
+
+    #[test]
+    fn coalescing_refreshes_received() {
+        let queue = Queue::default();
+        let stats = Arc::new(Stats::default());
+
+        let old = Instant::now() - LATENCY_BUDGET * 2;
+        queue.push(Job::Play {
+            event: RemoteControlEvent::MouseMove { dx: 1.0, dy: 0.0 },
+            slot: 0,
+            reply: None,
+            received: old,
+            stats: stats.clone()
+        });
+        play(&queue, 0, &stats, RemoteControlEvent::MouseMove { dx: 1.0, dy: 0.0 });
+
+        let jobs = queue.jobs.lock().unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert!(matches!(&jobs[0], Job::Play { received, .. } if received.elapsed() < LATENCY_BUDGET));
+    }
+}
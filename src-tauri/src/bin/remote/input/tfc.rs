@@ -0,0 +1,162 @@
+//! Input through `tfc`, which works on X11 (along with Windows and macOS).
+
+use super::{AnyKey, Backend};
+
+use ::tfc::traits::*;
+use shared::api::input::{Key, MediaKey, MouseButton};
+
+pub struct Tfc {
+    context: ::tfc::Context
+}
+
+impl Tfc {
+    pub fn new() -> anyhow::Result<Self> {
+        Ok(Tfc { context: ::tfc::Context::new()? })
+    }
+}
+
+impl Backend for Tfc {
+    fn name(&self) -> &'static str {
+        "tfc"
+    }
+
+    fn key_down(&mut self, key: AnyKey) -> anyhow::Result<()> {
+        Ok(self.context.key_down(tfc_any_key(key))?)
+    }
+
+    fn key_up(&mut self, key: AnyKey) -> anyhow::Result<()> {
+        Ok(self.context.key_up(tfc_any_key(key))?)
+    }
+
+    fn key_click(&mut self, key: AnyKey) -> anyhow::Result<()> {
+        Ok(self.context.key_click(tfc_any_key(key))?)
+    }
+
+    fn text(&mut self, text: &str) -> anyhow::Result<()> {
+        for ch in text.chars() {
+            match ch {
+                '\n' => {
+                    self.context.key_click(::tfc::Key::ReturnOrEnter)?;
+                },
+                ch => {
+                    self.context.unicode_char(ch)?;
+                }
+            }
+        }
+        // self.context.unicode_string(&text)?;
+
+        Ok(())
+    }
+
+    fn mouse_move(&mut self, dx: i32, dy: i32) -> anyhow::Result<()> {
+        Ok(self.context.mouse_move_rel(dx, dy)?)
+    }
+
+    fn mouse_position(&mut self, x: f64, y: f64) -> anyhow::Result<()> {
+        // The size of the whole screen, which spans every monitor on X11
+        let (width, height) = self.context.screen_size()?;
+        let x = (x * (width - 1).max(0) as f64).round() as i32;
+        let y = (y * (height - 1).max(0) as f64).round() as i32;
+
+        Ok(self.context.mouse_move_abs(x, y)?)
+    }
+
+    fn mouse_down(&mut self, button: MouseButton) -> anyhow::Result<()> {
+        Ok(self.context.mouse_down(tfc_button(button))?)
+    }
+
+    fn mouse_up(&mut self, button: MouseButton) -> anyhow::Result<()> {
+        Ok(self.context.mouse_up(tfc_button(button))?)
+    }
+
+    fn mouse_click(&mut self, button: MouseButton) -> anyhow::Result<()> {
+        Ok(self.context.mouse_click(tfc_button(button))?)
+    }
+
+    fn mouse_scroll(&mut self, dx: i32, dy: i32) -> anyhow::Result<()> {
+        Ok(self.context.mouse_scroll(dx, dy)?)
+    }
+}
+
+fn tfc_any_key(key: AnyKey) -> ::tfc::Key {
+    match key {
+        AnyKey::Key(key) => tfc_key(key),
+        AnyKey::Media(key) => tfc_media_key(key)
+    }
+}
+
+fn tfc_button(button: MouseButton) -> ::tfc::MouseButton {
+    use MouseButton::*;
+
+    match button {
+        LeftButton => ::tfc::MouseButton::Left,
+        RightButton => ::tfc::MouseButton::Right,
+        MiddleButton => ::tfc::MouseButton::Middle,
+    }
+}
+
+fn tfc_key(key: Key) -> ::tfc::Key {
+    use ::tfc::Key as Tfc;
+    use Key::*;
+
+    match key {
+        A => Tfc::A, B => Tfc::B, C => Tfc::C, D => Tfc::D,
+        E => Tfc::E, F => Tfc::F, G => Tfc::G, H => Tfc::H,
+        I => Tfc::I, J => Tfc::J, K => Tfc::K, L => Tfc::L,
+        M => Tfc::M, N => Tfc::N, O => Tfc::O, P => Tfc::P,
+        Q => Tfc::Q, R => Tfc::R, S => Tfc::S, T => Tfc::T,
+        U => Tfc::U, V => Tfc::V, W => Tfc::W, X => Tfc::X,
+        Y => Tfc::Y, Z => Tfc::Z,
+        Digit0 => Tfc::N0, Digit1 => Tfc::N1, Digit2 => Tfc::N2,
+        Digit3 => Tfc::N3, Digit4 => Tfc::N4, Digit5 => Tfc::N5,
+        Digit6 => Tfc::N6, Digit7 => Tfc::N7, Digit8 => Tfc::N8,
+        Digit9 => Tfc::N9,
+        F1 => Tfc::F1, F2 => Tfc::F2, F3 => Tfc::F3, F4 => Tfc::F4,
+        F5 => Tfc::F5, F6 => Tfc::F6, F7 => Tfc::F7, F8 => Tfc::F8,
+        F9 => Tfc::F9, F10 => Tfc::F10, F11 => Tfc::F11, F12 => Tfc::F12,
+        Minus => Tfc::Minus,
+        Equal => Tfc::Equal,
+        LeftBracket => Tfc::LeftBracket,
+        RightBracket => Tfc::RightBracket,
+        Backslash => Tfc::Backslash,
+        Semicolon => Tfc::Semicolon,
+        Quote => Tfc::Quote,
+        Grave => Tfc::Grave,
+        Comma => Tfc::Comma,
+        Period => Tfc::Period,
+        Slash => Tfc::Slash,
+        Tab => Tfc::Tab,
+        Enter => Tfc::ReturnOrEnter,
+        Escape => Tfc::Escape,
+        Space => Tfc::Space,
+        Backspace => Tfc::DeleteOrBackspace,
+        Delete => Tfc::ForwardDelete,
+        CapsLock => Tfc::CapsLock,
+        Up => Tfc::UpArrow,
+        Down => Tfc::DownArrow,
+        Left => Tfc::LeftArrow,
+        Right => Tfc::RightArrow,
+        Home => Tfc::Home,
+        End => Tfc::End,
+        PageUp => Tfc::PageUp,
+        PageDown => Tfc::PageDown,
+        Shift => Tfc::Shift,
+        Control => Tfc::Control,
+        Alt => Tfc::Alt,
+        Meta => Tfc::Meta,
+    }
+}
+
+fn tfc_media_key(key: MediaKey) -> ::tfc::Key {
+    use ::tfc::Key as Tfc;
+    use MediaKey::*;
+
+    match key {
+        PlayPause => Tfc::PlayPause,
+        FastForward => Tfc::FastForward,
+        Rewind => Tfc::Rewind,
+        VolumeUp => Tfc::VolumeUp,
+        VolumeDown => Tfc::VolumeDown,
+        Mute => Tfc::Mute,
+    }
+}
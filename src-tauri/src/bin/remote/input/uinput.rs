@@ -0,0 +1,311 @@
+//! Input through a virtual device made with `uinput`. The kernel sees it as
+//! just another keyboard and mouse, so it works with any display server (or
+//! none at all), as long as `/dev/uinput` is writable.
+
+use super::{AnyKey, Backend};
+
+use evdev::{
+    AbsInfo, AbsoluteAxisType, AttributeSet, EventType, InputEvent, RelativeAxisType,
+    UinputAbsSetup,
+    uinput::{VirtualDevice, VirtualDeviceBuilder}
+};
+use shared::api::input::{Key, MediaKey, MouseButton};
+
+const DEVICE_NAME: &str = "Macropinna remote";
+const POINTER_DEVICE_NAME: &str = "Macropinna remote pointer";
+
+const POSITION_MAX: i32 = 65535;
+
+pub struct Uinput {
+    device: VirtualDevice,
+    /// For absolute positions. A device can't reliably be both a relative
+    /// and an absolute pointer, so this is separate, and only made if needed.
+    pointer: Option<VirtualDevice>
+}
+
+impl Uinput {
+    pub fn new() -> anyhow::Result<Self> {
+        let mut keys = AttributeSet::<evdev::Key>::new();
+        for key in ALL_KEYS {
+            keys.insert(evdev_key(*key));
+        }
+        for key in ALL_MEDIA_KEYS {
+            keys.insert(evdev_media_key(*key));
+        }
+        for button in [MouseButton::LeftButton, MouseButton::RightButton, MouseButton::MiddleButton] {
+            keys.insert(evdev_button(button));
+        }
+
+        let axes = AttributeSet::from_iter([
+            RelativeAxisType::REL_X,
+            RelativeAxisType::REL_Y,
+            RelativeAxisType::REL_WHEEL,
+            RelativeAxisType::REL_HWHEEL
+        ]);
+
+        let device = VirtualDeviceBuilder::new()?
+            .name(DEVICE_NAME)
+            .with_keys(&keys)?
+            .with_relative_axes(&axes)?
+            .build()?;
+
+        Ok(Uinput { device, pointer: None })
+    }
+
+    /// An absolute pointer with mouse buttons and no touch, which compositors
+    /// map across the whole display, like a VM's tablet device.
+    fn new_pointer() -> anyhow::Result<VirtualDevice> {
+        let buttons = AttributeSet::from_iter(
+            [MouseButton::LeftButton, MouseButton::RightButton, MouseButton::MiddleButton]
+                .into_iter()
+                .map(evdev_button)
+        );
+        let position = AbsInfo::new(0, 0, POSITION_MAX, 0, 0, 0);
+
+        Ok(VirtualDeviceBuilder::new()?
+            .name(POINTER_DEVICE_NAME)
+            .with_keys(&buttons)?
+            .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisType::ABS_X, position))?
+            .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisType::ABS_Y, position))?
+            .build()?)
+    }
+
+    fn key(&mut self, key: evdev::Key, value: i32) -> anyhow::Result<()> {
+        Ok(self.device.emit(&[InputEvent::new(EventType::KEY, key.code(), value)])?)
+    }
+
+    fn relative(&mut self, events: &[(RelativeAxisType, i32)]) -> anyhow::Result<()> {
+        let events = events
+            .iter()
+            .filter(|(_, value)| *value != 0)
+            .map(|(axis, value)| InputEvent::new(EventType::RELATIVE, axis.0, *value))
+            .collect::<Vec<_>>();
+
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        Ok(self.device.emit(&events)?)
+    }
+}
+
+impl Backend for Uinput {
+    fn name(&self) -> &'static str {
+        "uinput"
+    }
+
+    fn key_down(&mut self, key: AnyKey) -> anyhow::Result<()> {
+        self.key(evdev_any_key(key), 1)
+    }
+
+    fn key_up(&mut self, key: AnyKey) -> anyhow::Result<()> {
+        self.key(evdev_any_key(key), 0)
+    }
+
+    fn text(&mut self, text: &str) -> anyhow::Result<()> {
+        for ch in text.chars() {
+            let (key, shift) = match char_key(ch) {
+                Some(key) => key,
+                None => {
+                    log::warn!("Can't type {:?} through uinput", ch);
+                    continue;
+                }
+            };
+
+            if shift {
+                super::chord(self, &[shared::api::input::Modifier::Shift], key)?;
+            }
+            else {
+                self.key_click(key.into())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn mouse_move(&mut self, dx: i32, dy: i32) -> anyhow::Result<()> {
+        self.relative(&[(RelativeAxisType::REL_X, dx), (RelativeAxisType::REL_Y, dy)])
+    }
+
+    fn mouse_position(&mut self, x: f64, y: f64) -> anyhow::Result<()> {
+        let pointer = match &mut self.pointer {
+            Some(pointer) => pointer,
+            None => self.pointer.insert(Self::new_pointer()?)
+        };
+
+        let abs = |axis: AbsoluteAxisType, value: f64| {
+            InputEvent::new(EventType::ABSOLUTE, axis.0, (value * POSITION_MAX as f64).round() as i32)
+        };
+
+        Ok(pointer.emit(&[abs(AbsoluteAxisType::ABS_X, x), abs(AbsoluteAxisType::ABS_Y, y)])?)
+    }
+
+    fn mouse_down(&mut self, button: MouseButton) -> anyhow::Result<()> {
+        self.key(evdev_button(button), 1)
+    }
+
+    fn mouse_up(&mut self, button: MouseButton) -> anyhow::Result<()> {
+        self.key(evdev_button(button), 0)
+    }
+
+    fn mouse_scroll(&mut self, dx: i32, dy: i32) -> anyhow::Result<()> {
+        // The wheel axis points up
+        self.relative(&[(RelativeAxisType::REL_HWHEEL, dx), (RelativeAxisType::REL_WHEEL, -dy)])
+    }
+}
+
+/// The key (and whether it needs Shift) for a character on a US layout.
+fn char_key(ch: char) -> Option<(Key, bool)> {
+    use Key::*;
+
+    const LETTERS: [Key; 26] = [A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z];
+    const DIGITS: [Key; 10] = [Digit0, Digit1, Digit2, Digit3, Digit4, Digit5, Digit6, Digit7, Digit8, Digit9];
+
+    let key = match ch {
+        'a'..='z' => (LETTERS[ch as usize - 'a' as usize], false),
+        'A'..='Z' => (LETTERS[ch as usize - 'A' as usize], true),
+        '0'..='9' => (DIGITS[ch as usize - '0' as usize], false),
+        ')' => (Digit0, true),
+        '!' => (Digit1, true),
+        '@' => (Digit2, true),
+        '#' => (Digit3, true),
+        '$' => (Digit4, true),
+        '%' => (Digit5, true),
+        '^' => (Digit6, true),
+        '&' => (Digit7, true),
+        '*' => (Digit8, true),
+        '(' => (Digit9, true),
+        '-' => (Minus, false),
+        '_' => (Minus, true),
+        '=' => (Equal, false),
+        '+' => (Equal, true),
+        '[' => (LeftBracket, false),
+        '{' => (LeftBracket, true),
+        ']' => (RightBracket, false),
+        '}' => (RightBracket, true),
+        '\\' => (Backslash, false),
+        '|' => (Backslash, true),
+        ';' => (Semicolon, false),
+        ':' => (Semicolon, true),
+        '\'' => (Quote, false),
+        '"' => (Quote, true),
+        '`' => (Grave, false),
+        '~' => (Grave, true),
+        ',' => (Comma, false),
+        '<' => (Comma, true),
+        '.' => (Period, false),
+        '>' => (Period, true),
+        '/' => (Slash, false),
+        '?' => (Slash, true),
+        ' ' => (Space, false),
+        '\t' => (Tab, false),
+        '\n' => (Enter, false),
+        _ => return None
+    };
+
+    Some(key)
+}
+
+const ALL_KEYS: &[Key] = {
+    use Key::*;
+
+    &[
+        A, B, C, D, E, F, G, H, I, J, K, L, M,
+        N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+        Digit0, Digit1, Digit2, Digit3, Digit4,
+        Digit5, Digit6, Digit7, Digit8, Digit9,
+        F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+        Minus, Equal, LeftBracket, RightBracket, Backslash,
+        Semicolon, Quote, Grave, Comma, Period, Slash,
+        Tab, Enter, Escape, Space, Backspace, Delete, CapsLock,
+        Up, Down, Left, Right, Home, End, PageUp, PageDown,
+        Shift, Control, Alt, Meta,
+    ]
+};
+
+const ALL_MEDIA_KEYS: &[MediaKey] = {
+    use MediaKey::*;
+
+    &[PlayPause, FastForward, Rewind, VolumeUp, VolumeDown, Mute]
+};
+
+fn evdev_any_key(key: AnyKey) -> evdev::Key {
+    match key {
+        AnyKey::Key(key) => evdev_key(key),
+        AnyKey::Media(key) => evdev_media_key(key)
+    }
+}
+
+fn evdev_key(key: Key) -> evdev::Key {
+    use evdev::Key as Ev;
+    use Key::*;
+
+    match key {
+        A => Ev::KEY_A, B => Ev::KEY_B, C => Ev::KEY_C, D => Ev::KEY_D,
+        E => Ev::KEY_E, F => Ev::KEY_F, G => Ev::KEY_G, H => Ev::KEY_H,
+        I => Ev::KEY_I, J => Ev::KEY_J, K => Ev::KEY_K, L => Ev::KEY_L,
+        M => Ev::KEY_M, N => Ev::KEY_N, O => Ev::KEY_O, P => Ev::KEY_P,
+        Q => Ev::KEY_Q, R => Ev::KEY_R, S => Ev::KEY_S, T => Ev::KEY_T,
+        U => Ev::KEY_U, V => Ev::KEY_V, W => Ev::KEY_W, X => Ev::KEY_X,
+        Y => Ev::KEY_Y, Z => Ev::KEY_Z,
+        Digit0 => Ev::KEY_0, Digit1 => Ev::KEY_1, Digit2 => Ev::KEY_2,
+        Digit3 => Ev::KEY_3, Digit4 => Ev::KEY_4, Digit5 => Ev::KEY_5,
+        Digit6 => Ev::KEY_6, Digit7 => Ev::KEY_7, Digit8 => Ev::KEY_8,
+        Digit9 => Ev::KEY_9,
+        F1 => Ev::KEY_F1, F2 => Ev::KEY_F2, F3 => Ev::KEY_F3, F4 => Ev::KEY_F4,
+        F5 => Ev::KEY_F5, F6 => Ev::KEY_F6, F7 => Ev::KEY_F7, F8 => Ev::KEY_F8,
+        F9 => Ev::KEY_F9, F10 => Ev::KEY_F10, F11 => Ev::KEY_F11, F12 => Ev::KEY_F12,
+        Minus => Ev::KEY_MINUS,
+        Equal => Ev::KEY_EQUAL,
+        LeftBracket => Ev::KEY_LEFTBRACE,
+        RightBracket => Ev::KEY_RIGHTBRACE,
+        Backslash => Ev::KEY_BACKSLASH,
+        Semicolon => Ev::KEY_SEMICOLON,
+        Quote => Ev::KEY_APOSTROPHE,
+        Grave => Ev::KEY_GRAVE,
+        Comma => Ev::KEY_COMMA,
+        Period => Ev::KEY_DOT,
+        Slash => Ev::KEY_SLASH,
+        Tab => Ev::KEY_TAB,
+        Enter => Ev::KEY_ENTER,
+        Escape => Ev::KEY_ESC,
+        Space => Ev::KEY_SPACE,
+        Backspace => Ev::KEY_BACKSPACE,
+        Delete => Ev::KEY_DELETE,
+        CapsLock => Ev::KEY_CAPSLOCK,
+        Up => Ev::KEY_UP,
+        Down => Ev::KEY_DOWN,
+        Left => Ev::KEY_LEFT,
+        Right => Ev::KEY_RIGHT,
+        Home => Ev::KEY_HOME,
+        End => Ev::KEY_END,
+        PageUp => Ev::KEY_PAGEUP,
+        PageDown => Ev::KEY_PAGEDOWN,
+        Shift => Ev::KEY_LEFTSHIFT,
+        Control => Ev::KEY_LEFTCTRL,
+        Alt => Ev::KEY_LEFTALT,
+        Meta => Ev::KEY_LEFTMETA,
+    }
+}
+
+fn evdev_media_key(key: MediaKey) -> evdev::Key {
+    use evdev::Key as Ev;
+    use MediaKey::*;
+
+    match key {
+        PlayPause => Ev::KEY_PLAYPAUSE,
+        FastForward => Ev::KEY_FASTFORWARD,
+        Rewind => Ev::KEY_REWIND,
+        VolumeUp => Ev::KEY_VOLUMEUP,
+        VolumeDown => Ev::KEY_VOLUMEDOWN,
+        Mute => Ev::KEY_MUTE,
+    }
+}
+
+fn evdev_button(button: MouseButton) -> evdev::Key {
+    match button {
+        MouseButton::LeftButton => evdev::Key::BTN_LEFT,
+        MouseButton::RightButton => evdev::Key::BTN_RIGHT,
+        MouseButton::MiddleButton => evdev::Key::BTN_MIDDLE,
+    }
+}
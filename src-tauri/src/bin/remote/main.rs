@@ -1,14 +1,28 @@
 use shared::{
-    config::load_from_path,
-    util::project_dirs::PROJECT_DIRS
+    config::{Config, load_from_path, profile::Profiles},
+    util::project_dirs
 };
+use std::sync::Arc;
 
+mod bindings;
 mod input;
 mod server;
 
 #[tokio::main]
 async fn main() {
-    let config = load_from_path(PROJECT_DIRS.config_dir().join("config.json")).unwrap();
+    let path = Profiles::new(project_dirs::config_dir()).active_config_path();
+    let config = load_from_path(path).unwrap_or_else(|| {
+        log::error!("Could not load the config, using the default one");
+        Config::default()
+    });
 
-    server::run(config).await;
-}
\ No newline at end of file
+    let bindings = Arc::new(bindings::BindingsState::new(&config));
+    let _watcher = bindings
+        .watch()
+        .map_err(|err| {
+            log::error!("Could not watch the config for changes: {}", err);
+        })
+        .ok();
+
+    server::run(config, bindings).await;
+}
@@ -7,10 +7,16 @@
 //! The external endpoint is what devices connect to, and the internal endpoint
 //! is what the Macropinna UI calls.
 
-use crate::input;
+use crate::{bindings::BindingsState, input};
 use shared::{
+    api::{
+        app::{self, AppMessage},
+        input::{MacroInfo, RemoteControlEvent, RemoteMessage},
+        remote::{Foreground, InputStats}
+    },
     config::Config,
-    util::project_dirs::PROJECT_DIRS
+    secrets::{self, SecretStore},
+    util::{numeric::moving_average, project_dirs}
 };
 
 use futures::stream::SplitSink;
@@ -19,10 +25,10 @@ use std::{
     collections::BTreeMap,
     convert::Infallible,
     path::PathBuf,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, atomic::{AtomicU64, Ordering}},
     time::{Instant, Duration},
 };
-use tokio::{sync::oneshot, time::timeout};
+use tokio::{sync::{mpsc, oneshot, watch}, time::timeout};
 use uuid::Uuid;
 use warp::{
     ws,
@@ -31,15 +37,26 @@ use warp::{
 
 const PENDING_TIMEOUT_S: u64 = 60_000;
 
+/// How often remotes get pinged, to measure round trip time.
+const PING_INTERVAL: Duration = Duration::from_secs(5);
+/// A remote that hasn't sent anything (pongs included) for this long is gone,
+/// even if the connection hasn't noticed yet.
+const DEAD_AFTER: Duration = Duration::from_secs(15);
+/// How much each new round trip counts toward the average.
+const RTT_WEIGHT: f64 = 0.25;
+
 struct ServerState {
     signer: Arc<String>,
     cert_path: Arc<PathBuf>,
     key_path: Arc<PathBuf>,
     input_ctx: input::Context,
+    bindings: Arc<BindingsState>,
     // State
     init_map: Mutex<BTreeMap<Uuid, AccessInit>>,
     pending_map: Mutex<BTreeMap<Uuid, AccessPending>>,
     active_map: Mutex<BTreeMap<Uuid, Active>>,
+    /// Id for the next WebSocket connection
+    next_connection: AtomicU64,
     // TODO store inactive devices (persistently) as well, this will require
     // require some sort of database to be added
 }
@@ -103,12 +120,21 @@ struct AccessPending {
 pub struct ActiveInfo {
     uuid: Uuid,
     name: String,
-    agent: Agent
+    agent: Agent,
+    input: InputStats,
+    /// Average round trip time of pings, in ms
+    rtt_ms: Option<f64>
 }
 
 struct Active {
     info: ActiveInfo,
-    _send: SplitSink<ws::WebSocket, ws::Message>
+    /// Which connection this is, as a device that reconnects replaces its
+    /// entry before its old connection is done with it
+    connection: u64,
+    /// Which player the remote is for gamepads, counting from 0
+    slot: usize,
+    stats: Arc<input::Stats>,
+    _send: mpsc::UnboundedSender<RemoteMessage>
 }
 
 impl ServerState {
@@ -149,28 +175,68 @@ impl ServerState {
         }
     }
 
-    /// Add an active connection
-    fn add_active(&self, claims: ActiveDeviceClaims, send: SplitSink<ws::WebSocket, ws::Message>) {
+    /// Add an active connection, returning its slot: the lowest one that isn't
+    /// taken, or the one it had if the device is reconnecting.
+    fn add_active(
+        &self,
+        claims: ActiveDeviceClaims,
+        connection: u64,
+        send: mpsc::UnboundedSender<RemoteMessage>,
+        stats: Arc<input::Stats>
+    ) -> usize {
         let info = ActiveInfo {
             uuid: claims.sub,
             name: claims.name,
-            agent: claims.agent
+            agent: claims.agent,
+            input: InputStats::default(),
+            rtt_ms: None
         };
 
         { // Lock for active_map
             let mut active_map = self.active_map.lock().unwrap();
+
+            let slot = match active_map.get(&claims.sub) {
+                Some(active) => active.slot,
+                None => (0..)
+                    .find(|slot| active_map.values().all(|active| active.slot != *slot))
+                    .unwrap()
+            };
+
             active_map.insert(claims.sub.clone(), Active {
                 info: info.clone(),
+                connection,
+                slot,
+                stats,
                 _send: send
             });
+
+            slot
         }
     }
 
-    /// Remove an active conncetion
-    fn remove_active(&self, uuid: &Uuid) {
+    /// Add a ping's round trip time to an active connection's average
+    fn record_rtt(&self, uuid: &Uuid, connection: u64, rtt: Duration) {
         { // Lock for active_map
             let mut active_map = self.active_map.lock().unwrap();
-            active_map.remove(uuid);
+            if let Some(active) = active_map.get_mut(uuid).filter(|active| active.connection == connection) {
+                let rtt_ms = rtt.as_secs_f64() * 1000.0;
+                active.info.rtt_ms = Some(moving_average(active.info.rtt_ms, rtt_ms, RTT_WEIGHT));
+            }
+        }
+    }
+
+    /// Remove an active connection, unless the device has connected again
+    /// since. Returns whether it was removed.
+    fn remove_active(&self, uuid: &Uuid, connection: u64) -> bool {
+        { // Lock for active_map
+            let mut active_map = self.active_map.lock().unwrap();
+            match active_map.get(uuid) {
+                Some(active) if active.connection == connection => {
+                    active_map.remove(uuid);
+                    true
+                }
+                _ => false
+            }
         }
     }
 }
@@ -214,23 +280,37 @@ impl ActiveDeviceClaims {
     }
 }
 
-pub async fn run(config: Config) {
-    let cert_path = PROJECT_DIRS.config_dir().join("cert.pem");
-    let key_path = PROJECT_DIRS.config_dir().join("key.pem");
+pub async fn run(config: Config, bindings: Arc<BindingsState>) {
+    let cert_path = project_dirs::config_dir().join("cert.pem");
+    let key_path = project_dirs::config_dir().join("key.pem");
 
     ensure_cert(&cert_path, &key_path).map_err(|err| {
         log::error!("Error while generating cert: {}", err);
     }).unwrap();
 
+    let signer = SecretStore::open()
+        .get_or_generate(secrets::JWT_SECRET, || {
+            format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+        })
+        .map_err(|err| {
+            log::error!("Error while loading JWT secret: {}", err);
+        })
+        .unwrap();
+
     let state = Arc::new(ServerState {
-        // TODO unhardcode
-        signer: Arc::new("secret".to_string()),
+        signer: Arc::new(signer),
         cert_path: Arc::new(cert_path),
         key_path: Arc::new(key_path),
-        input_ctx: input::Context::new(),
+        input_ctx: input::Context::new(
+            config.remote_server.input_backend(),
+            config.remote_server.port_app(),
+            bindings.clone()
+        ),
+        bindings,
         init_map: Mutex::new(BTreeMap::new()),
         pending_map: Mutex::new(BTreeMap::new()),
         active_map: Mutex::new(BTreeMap::new()),
+        next_connection: AtomicU64::new(0),
     });
 
     let remote_static_path = "./remote-static";
@@ -291,13 +371,31 @@ pub async fn run(config: Config) {
         .and(with_state(state.clone()))
         .map(handle_current_active);
 
+    // <internal>/api/foreground: set the launcher in the foreground
+    let foreground = warp::path!("api" / "foreground")
+        .and(warp::post())
+        .and(warp::body::json::<Foreground>())
+        .and(with_state(state.clone()))
+        .map(handle_foreground);
+
     let routes = approve
         .or(reject)
         .or(current_pending)
-        .or(current_active);
+        .or(current_active)
+        .or(foreground);
 
+    // Bound right away, so the app can answer on it
     let internal = warp::serve(routes)
-        .run(([127, 0, 0, 1], config.remote_server.port_internal));
+        .bind(([127, 0, 0, 1], config.remote_server.port_internal));
+
+    // The app could already have a launcher in the foreground, if it was
+    // running before this
+    let port_app = config.remote_server.port_app();
+    tokio::task::spawn_blocking(move || {
+        if let Err(err) = app::send(port_app, &AppMessage::GetForeground) {
+            log::debug!("Could not ask the app for the foreground launcher: {}", err);
+        }
+    });
 
     futures::join!(external, internal);
 }
@@ -448,24 +546,56 @@ async fn handle_ws_connection(socket: ws::WebSocket, claims: ActiveDeviceClaims,
     use futures::StreamExt;
     
     let (ws_send, mut ws_recv) = socket.split();
+    let (send, recv) = mpsc::unbounded_channel();
+    // Pings carry the time they were sent, relative to this
+    let epoch = Instant::now();
+    tokio::spawn(send_messages(
+        ws_send,
+        recv,
+        state.bindings.subscribe_macros(),
+        state.input_ctx.subscribe_available(),
+        epoch
+    ));
 
     let name = claims.name.clone();
     let uuid = claims.sub.clone();
-    state.add_active(claims, ws_send);
+    let stats = Arc::new(input::Stats::default());
+    let connection = state.next_connection.fetch_add(1, Ordering::Relaxed);
+    let slot = state.add_active(claims, connection, send.clone(), stats.clone());
+    let _ = send.send(RemoteMessage::Player(slot + 1));
+
+    loop {
+        let msg = match timeout(DEAD_AFTER, ws_recv.next()).await {
+            Ok(Some(msg)) => msg,
+            Ok(None) => break,
+            Err(_) => {
+                log::info!("{} ({}) stopped answering pings, closing", name, uuid);
+                break;
+            }
+        };
 
-    while let Some(msg) = ws_recv.next().await {
         match msg {
             Ok(msg) if msg.is_text() => {
                 let msg = msg.to_str().unwrap();
-                match serde_json::from_str::<input::RemoteControlEvent>(msg) {
+                match serde_json::from_str::<RemoteControlEvent>(msg) {
                     Ok(event) => {
-                        state.input_ctx.play_event(event);
+                        state.input_ctx.play_event(event, slot, Some(send.clone()), stats.clone());
                     }
                     Err(err) => {
                         log::error!("Error while deserializing WebSocket message \"{}\": {}", msg, err);
                     }
                 }
             },
+            Ok(msg) if msg.is_pong() => {
+                let sent = <[u8; 8]>::try_from(msg.as_bytes()).ok().map(u64::from_be_bytes);
+                match sent {
+                    Some(sent) => {
+                        let rtt = epoch.elapsed().saturating_sub(Duration::from_micros(sent));
+                        state.record_rtt(&uuid, connection, rtt);
+                    }
+                    None => log::error!("Got a pong with no timestamp from {} ({})", name, uuid)
+                }
+            },
             Err(err) => {
                 log::error!("Error while handling WebSocket connection: {}", err);
             },
@@ -475,7 +605,66 @@ async fn handle_ws_connection(socket: ws::WebSocket, claims: ActiveDeviceClaims,
     }
 
     log::info!("Connection dropped for {} ({})", name, uuid);
-    state.remove_active(&uuid);
+    // A newer connection from the same device keeps its slot (and gamepad)
+    if state.remove_active(&uuid, connection) {
+        state.input_ctx.release(slot);
+    }
+}
+
+/// Send messages to a remote: the current macros and whether input is
+/// available (and any changes to those), whatever else comes through `recv`,
+/// and a ping every `PING_INTERVAL` with the time since `epoch` in
+/// microseconds. Closes the socket once every sender is gone.
+async fn send_messages(
+    mut ws_send: SplitSink<ws::WebSocket, ws::Message>,
+    mut recv: mpsc::UnboundedReceiver<RemoteMessage>,
+    mut macros: watch::Receiver<Vec<MacroInfo>>,
+    mut available: watch::Receiver<bool>,
+    epoch: Instant
+) {
+    use futures::SinkExt;
+
+    let text = |message: RemoteMessage| {
+        ws::Message::text(serde_json::to_string(&message).unwrap())
+    };
+
+    let mut messages = vec![
+        text(RemoteMessage::Macros(macros.borrow_and_update().clone())),
+        text(RemoteMessage::InputAvailable(*available.borrow_and_update()))
+    ];
+    let mut ping = tokio::time::interval(PING_INTERVAL);
+
+    'send: loop {
+        for message in messages.drain(..) {
+            if let Err(err) = ws_send.send(message).await {
+                log::error!("Error while sending WebSocket message: {}", err);
+                break 'send;
+            }
+        }
+
+        let message = tokio::select! {
+            received = recv.recv() => match received {
+                Some(received) => text(received),
+                None => break
+            },
+            changed = macros.changed() => match changed {
+                Ok(()) => text(RemoteMessage::Macros(macros.borrow_and_update().clone())),
+                // Bindings are never dropped, but don't spin if they were
+                Err(_) => break
+            },
+            changed = available.changed() => match changed {
+                Ok(()) => text(RemoteMessage::InputAvailable(*available.borrow_and_update())),
+                Err(_) => break
+            },
+            _ = ping.tick() => {
+                let sent = epoch.elapsed().as_micros() as u64;
+                ws::Message::ping(sent.to_be_bytes().to_vec())
+            }
+        };
+        messages.push(message);
+    }
+
+    let _ = ws_send.close().await;
 }
 
 fn handle_approve(uuid: Uuid, state: Arc<ServerState>) -> impl Reply {
@@ -528,8 +717,17 @@ fn handle_current_pending(state: Arc<ServerState>) -> impl Reply {
 fn handle_current_active(state: Arc<ServerState>) -> impl Reply {
     let list = { // Lock for state.active_map
         let active_map = state.active_map.lock().unwrap();
-        active_map.values().map(|val| val.info.clone()).collect::<Vec<_>>()
+        active_map
+            .values()
+            .map(|val| ActiveInfo { input: val.stats.snapshot(), ..val.info.clone() })
+            .collect::<Vec<_>>()
     };
 
     warp::reply::json(&list)
+}
+
+fn handle_foreground(foreground: Foreground, state: Arc<ServerState>) -> impl Reply {
+    state.bindings.set_foreground(foreground.launcher);
+
+    warp::reply::json(&true)
 }
\ No newline at end of file
@@ -0,0 +1,154 @@
+//! What the remote's D-pad and action buttons do.
+
+use super::input::{Action, DPadDirection, Key, Modifier};
+
+use schemars::JsonSchema;
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+
+/// A key, with modifiers held down while it's clicked.
+#[serde_with::skip_serializing_none]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct KeyChord {
+    pub modifiers: Option<Vec<Modifier>>,
+    pub key: Key
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub enum Binding {
+    /// Click each chord in turn
+    Keys(Vec<KeyChord>),
+    /// Launch (or raise) a launcher, by name
+    Launcher(String),
+    /// Run a command through the shell
+    Command(String),
+    /// Go back to the home screen
+    Home,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct BindingSet {
+    pub dpad: Option<BTreeMap<DPadDirection, Binding>>,
+    pub actions: Option<BTreeMap<Action, Binding>>,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Bindings {
+    /// Overrides for the built-in bindings.
+    pub default: Option<BindingSet>,
+    /// Overrides for while a launcher is in the foreground, keyed by launcher
+    /// name. Anything not bound here falls back to `default`.
+    pub launchers: Option<BTreeMap<String, BindingSet>>,
+}
+
+impl Bindings {
+    /// What a D-pad button does while `launcher` is in the foreground.
+    pub fn dpad(&self, launcher: Option<&str>, direction: DPadDirection) -> Binding {
+        self.find(launcher, |set| set.dpad.as_ref()?.get(&direction))
+            .unwrap_or_else(|| {
+                let key = match direction {
+                    DPadDirection::Up => Key::Up,
+                    DPadDirection::Down => Key::Down,
+                    DPadDirection::Left => Key::Left,
+                    DPadDirection::Right => Key::Right,
+                    DPadDirection::Enter => Key::Space,
+                    DPadDirection::Exit => Key::Escape,
+                };
+
+                Binding::Keys(vec![KeyChord { modifiers: None, key }])
+            })
+    }
+
+    /// What an action button does while `launcher` is in the foreground.
+    pub fn action(&self, launcher: Option<&str>, action: Action) -> Binding {
+        self.find(launcher, |set| set.actions.as_ref()?.get(&action))
+            .unwrap_or_else(|| match action {
+                Action::Home => Binding::Home,
+                Action::AltTab => Binding::Keys(vec![KeyChord {
+                    modifiers: Some(vec![Modifier::Alt]),
+                    key: Key::Tab
+                }])
+            })
+    }
+
+    fn find<F>(&self, launcher: Option<&str>, get: F) -> Option<Binding>
+    where
+        F: Fn(&BindingSet) -> Option<&Binding>
+    {
+        let launcher_set = launcher.and_then(|name| self.launchers.as_ref()?.get(name));
+
+        launcher_set
+            .and_then(&get)
+            .or_else(|| self.default.as_ref().and_then(&get))
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(direction: DPadDirection, launcher: &str) -> BindingSet {
+        BindingSet {
+            dpad: Some(BTreeMap::from([(direction, Binding::Launcher(launcher.to_string()))])),
+            actions: None
+        }
+    }
+
+    fn bindings() -> Bindings {
+        Bindings {
+            default: Some(set(DPadDirection::Up, "default")),
+            launchers: Some(BTreeMap::from([
+                ("game".to_string(), set(DPadDirection::Down, "game"))
+            ]))
+        }
+    }
+
+    fn launcher_name(binding: Binding) -> Option<String> {
+        match binding {
+            Binding::Launcher(name) => Some(name),
+            _ => None
+        }
+    }
+
+    #[test]
+    fn launcher_set_comes_first() {
+        let binding = bindings().dpad(Some("game"), DPadDirection::Down);
+        assert_eq!(launcher_name(binding).as_deref(), Some("game"));
+    }
+
+    #[test]
+    fn falls_back_to_default_set() {
+        let bindings = bindings();
+
+        // Not bound for the launcher
+        let binding = bindings.dpad(Some("game"), DPadDirection::Up);
+        assert_eq!(launcher_name(binding).as_deref(), Some("default"));
+
+        // A launcher without overrides
+        let binding = bindings.dpad(Some("other"), DPadDirection::Up);
+        assert_eq!(launcher_name(binding).as_deref(), Some("default"));
+    }
+
+    #[test]
+    fn launcher_sets_need_a_launcher() {
+        let binding = bindings().dpad(None, DPadDirection::Down);
+        assert!(matches!(
+            binding,
+            Binding::Keys(chords) if chords.len() == 1 && matches!(chords[0].key, Key::Down)
+        ));
+    }
+
+    #[test]
+    fn falls_back_to_built_in() {
+        let bindings = Bindings::default();
+
+        assert!(matches!(bindings.action(Some("game"), Action::Home), Binding::Home));
+        assert!(matches!(
+            bindings.dpad(None, DPadDirection::Enter),
+            Binding::Keys(chords) if matches!(chords[0].key, Key::Space)
+        ));
+    }
+}
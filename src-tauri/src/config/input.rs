@@ -0,0 +1,170 @@
+//! Input events sent by remotes. These live with the config rather than in
+//! `api::input`, since bindings and macros are made of them and the config is
+//! also compiled on its own into the build script.
+
+use schemars::JsonSchema;
+use serde::{Serialize, Deserialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub enum RemoteControlEvent {
+    DPad(DPadDirection),
+    Text(String),
+    /// Click a single key.
+    Keyboard(Key),
+    /// Hold `modifiers` down (in order) while clicking `key`, e.g. Ctrl+C.
+    Chord {
+        modifiers: Vec<Modifier>,
+        key: Key
+    },
+    Media(MediaKey),
+    /// Move the pointer relative to where it is. The remote server's pointer
+    /// settings apply to moves from remotes, but not from macros.
+    MouseMove {
+        dx: f64,
+        dy: f64,
+    },
+    /// Move the pointer to a point on the display, from (0, 0) at the top left
+    /// to (1, 1) at the bottom right.
+    MousePosition {
+        x: f64,
+        y: f64,
+    },
+    MouseDown(MouseButton),
+    MouseUp(MouseButton),
+    MouseClick(MouseButton),
+    MouseScroll {
+        dx: f64,
+        dy: f64
+    },
+    Action(Action),
+    /// Run a macro, by name.
+    Macro(String),
+    /// Input on a virtual gamepad. Every remote gets a gamepad of its own.
+    Gamepad(GamepadEvent),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
+pub enum DPadDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+    Enter,
+    Exit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
+pub enum Action {
+    Home,
+    AltTab,
+}
+
+/// Named after the keys on a US layout, since that's what `tfc` goes by.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, JsonSchema)]
+pub enum Key {
+    A, B, C, D, E, F, G, H, I, J, K, L, M,
+    N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Digit0, Digit1, Digit2, Digit3, Digit4,
+    Digit5, Digit6, Digit7, Digit8, Digit9,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    Minus,
+    Equal,
+    LeftBracket,
+    RightBracket,
+    Backslash,
+    Semicolon,
+    Quote,
+    Grave,
+    Comma,
+    Period,
+    Slash,
+    Tab,
+    Enter,
+    Escape,
+    Space,
+    Backspace,
+    Delete,
+    CapsLock,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Shift,
+    Control,
+    Alt,
+    Meta,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, JsonSchema)]
+pub enum Modifier {
+    Shift,
+    Control,
+    Alt,
+    Meta,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, JsonSchema)]
+pub enum MediaKey {
+    PlayPause,
+    FastForward,
+    Rewind,
+    VolumeUp,
+    VolumeDown,
+    Mute,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, JsonSchema)]
+pub enum MouseButton {
+    LeftButton,
+    RightButton,
+    MiddleButton
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub enum GamepadEvent {
+    Button {
+        button: GamepadButton,
+        pressed: bool
+    },
+    /// From -1 to 1 on each axis, with positive `y` pointing down.
+    Stick {
+        stick: Side,
+        x: f64,
+        y: f64
+    },
+    /// From 0 (released) to 1 (all the way down).
+    Trigger {
+        trigger: Side,
+        value: f64
+    },
+}
+
+/// Named after an Xbox controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
+pub enum GamepadButton {
+    A,
+    B,
+    X,
+    Y,
+    LeftBumper,
+    RightBumper,
+    Back,
+    Start,
+    Guide,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum Side {
+    Left,
+    Right,
+}
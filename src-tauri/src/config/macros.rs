@@ -0,0 +1,34 @@
+//! Named sequences of remote input, shown on remotes as custom buttons.
+
+use super::{bindings::Binding, input::RemoteControlEvent};
+
+use schemars::JsonSchema;
+use serde::{Serialize, Deserialize};
+
+/// The longest a macro can spend in `Delay` steps, all together. Macros run
+/// on the input worker, so nothing else from any remote gets played while one
+/// waits.
+pub const MAX_DELAY_MS: u64 = 10_000;
+
+#[serde_with::skip_serializing_none]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Macro {
+    pub name: String,
+    /// Iconify icon for the button, e.g. `carbon:play`. Buttons without an
+    /// icon show the name instead.
+    pub icon: Option<String>,
+    pub steps: Vec<MacroStep>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub enum MacroStep {
+    /// Play an input event, as if it came from the remote
+    Event(RemoteControlEvent),
+    /// Do what a button could be bound to, e.g. start a launcher
+    Run(Binding),
+    /// Wait before the next step. A macro's delays can add up to at most 10
+    /// seconds
+    Delay {
+        ms: u64
+    },
+}
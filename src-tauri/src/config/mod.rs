@@ -1,5 +1,12 @@
+use schemars::JsonSchema;
 use serde::{Serialize, Deserialize};
-use std::path::Path;
+use std::{collections::BTreeMap, path::Path};
+
+pub mod bindings;
+pub mod input;
+pub mod macros;
+pub mod profile;
+pub mod widgets;
 
 /// Trait to provide a versioning chain for config files. Ensures that a config
 /// file written for any config version can be safely used and upgraded to the
@@ -40,7 +47,8 @@ impl<'a> VersionedDeserialize<'a> for () {
 }
 
 #[serde_with::skip_serializing_none]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "Config")]
 pub struct ConfigV1 {
     pub name: String,
     pub theme: Theme,
@@ -50,33 +58,53 @@ pub struct ConfigV1 {
     pub weather: Option<Weather>,
     pub audio_device: Option<AudioDevice>,
     pub remote_server: RemoteServer,
+    /// What the remote's buttons do. Defaults to arrow keys for the D-pad,
+    /// Space for Enter and Escape for Exit.
+    pub bindings: Option<bindings::Bindings>,
+    /// Shown on remotes as custom buttons.
+    pub macros: Option<Vec<macros::Macro>>,
     pub needs_setup: Option<bool>
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct Theme {
     color: String,
     style: String
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde_with::skip_serializing_none]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct Home {
-    pub screens: Vec<Screen>
+    pub screens: Vec<Screen>,
+    /// What happens to the app in the foreground when the remote goes back
+    /// home. Defaults to `Keep`.
+    pub foreground_on_home: Option<ForegroundOnHome>
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, JsonSchema)]
+pub enum ForegroundOnHome {
+    /// Leave it be, the home screen just goes on top
+    Keep,
+    /// Minimize its windows
+    Minimize,
+    /// Pause its processes until it's launched again
+    Pause
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct Screen {
     pub widgets: Vec<Widget>
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+// `JsonSchema` is implemented by hand in `widgets`, to type `props` per widget
 pub struct Widget {
     pub name: String,
     pub coords: Coords,
     pub props: Option<serde_json::Value>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct Coords {
     x: f64,
     y: f64,
@@ -85,45 +113,143 @@ pub struct Coords {
 }
 
 #[serde_with::skip_serializing_none]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct Launcher {
     pub name: String,
     pub command: String,
+    /// Title of the launcher's window, to raise it instead of launching again
+    /// if it's already open. Ignored if any of the other finders are set.
     pub finder: String,
     pub finder_is_regex: Option<bool>,
+    /// Find the launcher's window by its app ID (`WM_CLASS` on X11).
+    pub finder_app_id: Option<String>,
+    /// Find the launcher's window by the name of the program that owns it.
+    pub finder_process_name: Option<String>,
+    /// Find the launcher's window by the path of the program that owns it.
+    pub finder_exe: Option<String>,
     pub image_path: Option<String>,
     pub css_background: Option<String>,
+    /// Extra arguments for `command`.
+    pub args: Option<Vec<String>>,
+    /// Extra environment variables, on top of the app's own environment.
+    pub env: Option<BTreeMap<String, String>>,
+    /// Working directory to launch in.
+    pub cwd: Option<String>,
+    /// Whether to run `command` through the shell (the default). Without the
+    /// shell, `command` is the program itself and `args` are passed as-is.
+    pub use_shell: Option<bool>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde_with::skip_serializing_none]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct Weather {
     pub provider: WeatherProvider,
-    pub api_key: String,
+    pub api_key: Option<Secret>,
     pub lat: f64,
     pub long: f64,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// A sensitive value. Configs only store the name of the secret, and the value
+/// itself lives in a `SecretStore`. Plain values are still accepted (older
+/// configs, or a key that was just typed into the UI), but get moved into the
+/// store the next time the config is saved.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Secret {
+    Stored {
+        secret: String
+    },
+    Inline(String)
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub enum WeatherProvider {
     OpenWeatherMap
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct AudioDevice {
     pub name: String
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde_with::skip_serializing_none]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct RemoteServer {
     pub port: u16,
-    pub port_internal: u16
+    pub port_internal: u16,
+    /// Localhost port the app listens on for the remote server. Defaults to
+    /// `DEFAULT_PORT_APP`.
+    pub port_app: Option<u16>,
+    /// How the remote server injects input. Defaults to `Auto`.
+    pub input_backend: Option<InputBackend>,
+    /// How relative pointer movement from the touchpad is scaled.
+    pub pointer: Option<Pointer>
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, JsonSchema)]
+pub enum InputBackend {
+    /// `Uinput` on Wayland or without a display server, `Tfc` otherwise
+    Auto,
+    /// Through the display server, only works on X11 on Linux
+    Tfc,
+    /// Virtual devices, Linux only. Needs write access to `/dev/uinput`, and
+    /// typed text goes by a US keyboard layout
+    Uinput
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Pointer {
+    /// Multiplier on every movement. Defaults to 1.
+    pub speed: Option<f64>,
+    /// Exponent on the length of each movement, so fast swipes go further
+    /// than slow ones. 1 (the default) is linear, and around 1.5 feels like a
+    /// laptop touchpad.
+    pub acceleration: Option<f64>
+}
+
+impl Pointer {
+    pub fn speed(&self) -> f64 {
+        self.speed.unwrap_or(1.0)
+    }
+
+    pub fn acceleration(&self) -> f64 {
+        self.acceleration.unwrap_or(1.0)
+    }
+
+    /// Apply the acceleration curve to a relative movement, keeping its
+    /// direction.
+    pub fn accelerate(&self, dx: f64, dy: f64) -> (f64, f64) {
+        let length = dx.hypot(dy);
+        if !length.is_normal() {
+            return (0.0, 0.0);
+        }
+
+        let scale = self.speed() * length.powf(self.acceleration() - 1.0);
+        (dx * scale, dy * scale)
+    }
+}
+
+pub const DEFAULT_PORT_APP: u16 = 51741;
+
+impl RemoteServer {
+    pub fn port_app(&self) -> u16 {
+        self.port_app.unwrap_or(DEFAULT_PORT_APP)
+    }
+
+    pub fn input_backend(&self) -> InputBackend {
+        self.input_backend.unwrap_or(InputBackend::Auto)
+    }
 }
 
 impl Default for RemoteServer {
     fn default() -> Self {
         RemoteServer {
             port: 5174,
-            port_internal: 51740
+            port_internal: 51740,
+            port_app: None,
+            input_backend: None,
+            pointer: None
         }
     }
 }
@@ -167,6 +293,10 @@ pub fn save_to_path<P: AsRef<Path>>(path: P, config: &Config) {
     }
 }
 
+/// Load the config the app runs on. Validation problems are only logged, so a
+/// config that was hand-edited into something invalid still loads: configs the
+/// app saves itself are validated before saving, and imports go through
+/// `parse`.
 pub fn load_from_path<P: AsRef<Path> + Clone>(path: P) -> Option<Config> {
     use std::fs::read;
 
@@ -180,9 +310,244 @@ pub fn load_from_path<P: AsRef<Path> + Clone>(path: P) -> Option<Config> {
         }
     };
 
-    Config::versioned_deserialize(&config)
+    let config = Config::versioned_deserialize(&config)
         .map_err(|err| {
             log::error!("Error while reading config file: {}", err);
         })
-        .ok()
-}
\ No newline at end of file
+        .ok()?;
+
+    for error in config.validate().into_iter().chain(config.lint()) {
+        log::warn!("Problem in config file: {}", error);
+    }
+
+    Some(config)
+}
+
+/// Parse a config from raw bytes, upgrading it from older config versions and
+/// validating the result. Configs from outside the app (like imports) should
+/// go through here.
+pub fn parse(bytes: &[u8]) -> anyhow::Result<Config> {
+    let config = Config::versioned_deserialize(bytes)?;
+    config.check()?;
+
+    Ok(config)
+}
+
+impl Config {
+    /// Check for problems that serde can't catch. Returns a list of
+    /// human-readable errors, empty if the config is valid.
+    ///
+    /// Dangling references aren't errors (see `lint`), or deleting a launcher
+    /// would mean first hunting down every binding to it.
+    pub fn validate(&self) -> Vec<String> {
+        use std::collections::BTreeSet;
+
+        let mut errors = vec![];
+
+        let mut names = BTreeSet::new();
+        for launcher in &self.launchers {
+            if launcher.name.is_empty() {
+                errors.push("launcher with an empty name".to_string());
+            }
+            else if !names.insert(&launcher.name) {
+                errors.push(format!("duplicate launcher \"{}\"", launcher.name));
+            }
+        }
+
+        for screen in &self.home.screens {
+            for widget in &screen.widgets {
+                let Coords { x, y, w, h } = widget.coords;
+                if ![x, y, w, h].iter().all(|n| n.is_finite() && *n >= 0.0) {
+                    errors.push(format!("widget \"{}\" has invalid coords", widget.name));
+                }
+            }
+        }
+
+        let mut macro_names = BTreeSet::new();
+        for m in self.macros.iter().flatten() {
+            if m.name.is_empty() {
+                errors.push("macro with an empty name".to_string());
+            }
+            else if !macro_names.insert(&m.name) {
+                errors.push(format!("duplicate macro \"{}\"", m.name));
+            }
+
+            let mut delay_ms: u64 = 0;
+            for step in &m.steps {
+                match step {
+                    macros::MacroStep::Event(input::RemoteControlEvent::Macro(_)) => {
+                        errors.push(format!("macro \"{}\" runs another macro", m.name));
+                    }
+                    macros::MacroStep::Delay { ms } => delay_ms = delay_ms.saturating_add(*ms),
+                    _ => {}
+                }
+            }
+            if delay_ms > macros::MAX_DELAY_MS {
+                errors.push(format!(
+                    "macro \"{}\" waits {} ms in total, more than the {} ms allowed",
+                    m.name,
+                    delay_ms,
+                    macros::MAX_DELAY_MS
+                ));
+            }
+        }
+
+        let ports = [
+            self.remote_server.port,
+            self.remote_server.port_internal,
+            self.remote_server.port_app()
+        ];
+        if ports[0] == ports[1] || ports[0] == ports[2] || ports[1] == ports[2] {
+            errors.push("remote server ports must be different".to_string());
+        }
+
+        errors
+    }
+
+    /// Check for problems that don't keep the config from working: bindings
+    /// and macros that start launchers that don't exist (they do nothing).
+    pub fn lint(&self) -> Vec<String> {
+        let mut targets = vec![];
+        if let Some(bindings) = &self.bindings {
+            let sets = bindings.default
+                .iter()
+                .chain(bindings.launchers.iter().flat_map(|launchers| launchers.values()));
+            for set in sets {
+                targets.extend(set.dpad.iter().flat_map(|dpad| dpad.values()));
+                targets.extend(set.actions.iter().flat_map(|actions| actions.values()));
+            }
+        }
+        for m in self.macros.iter().flatten() {
+            for step in &m.steps {
+                if let macros::MacroStep::Run(binding) = step {
+                    targets.push(binding);
+                }
+            }
+        }
+
+        let mut warnings = vec![];
+        for target in targets {
+            if let bindings::Binding::Launcher(name) = target {
+                if !self.launchers.iter().any(|launcher| launcher.name == *name) {
+                    warnings.push(format!("unknown launcher \"{}\" in bindings or macros", name));
+                }
+            }
+        }
+
+        warnings
+    }
+
+    /// `validate`, as a single error.
+    pub fn check(&self) -> anyhow::Result<()> {
+        let errors = self.validate();
+        if !errors.is_empty() {
+            anyhow::bail!("invalid config: {}", errors.join("; "));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn launcher(name: &str) -> Launcher {
+        serde_json::from_value(json!({ "name": name, "command": name, "finder": name })).unwrap()
+    }
+
+    fn launcher_binding(name: &str) -> bindings::Bindings {
+        serde_json::from_value(json!({
+            "default": { "actions": { "Home": { "Launcher": name } } }
+        })).unwrap()
+    }
+
+    fn macro_with(steps: serde_json::Value) -> macros::Macro {
+        serde_json::from_value(json!({ "name": "test", "steps": steps })).unwrap()
+    }
+
+    #[test]
+    fn default_config_is_valid() {
+        let config = Config::default();
+        assert!(config.validate().is_empty());
+        assert!(config.lint().is_empty());
+    }
+
+    #[test]
+    fn duplicate_launchers_are_invalid() {
+        let config = Config {
+            launchers: vec![launcher("a"), launcher("a")],
+            ..Config::default()
+        };
+
+        assert_eq!(config.validate(), vec!["duplicate launcher \"a\"".to_string()]);
+        assert!(config.check().is_err());
+    }
+
+    #[test]
+    fn dangling_launcher_references_are_only_linted() {
+        let mut config = Config {
+            launchers: vec![launcher("a")],
+            bindings: Some(launcher_binding("gone")),
+            ..Config::default()
+        };
+
+        assert!(config.validate().is_empty());
+        assert!(config.check().is_ok());
+        assert_eq!(config.lint(), vec!["unknown launcher \"gone\" in bindings or macros".to_string()]);
+
+        config.bindings = Some(launcher_binding("a"));
+        assert!(config.lint().is_empty());
+    }
+
+    #[test]
+    fn macro_delays_are_limited_in_total() {
+        let mut config = Config::default();
+
+        let half = macros::MAX_DELAY_MS / 2;
+        config.macros = Some(vec![macro_with(json!([{ "Delay": { "ms": half } }, { "Delay": { "ms": half } }]))]);
+        assert!(config.validate().is_empty());
+
+        config.macros = Some(vec![macro_with(json!([{ "Delay": { "ms": half } }, { "Delay": { "ms": half + 1 } }]))]);
+        assert_eq!(config.validate().len(), 1);
+    }
+
+    #[test]
+    fn macros_cant_run_macros() {
+        let config = Config {
+            macros: Some(vec![macro_with(json!([{ "Event": { "Macro": "test" } }]))]),
+            ..Config::default()
+        };
+
+        assert_eq!(config.validate(), vec!["macro \"test\" runs another macro".to_string()]);
+    }
+
+    #[test]
+    fn default_pointer_is_linear() {
+        assert_eq!(Pointer::default().accelerate(3.0, -4.0), (3.0, -4.0));
+    }
+
+    #[test]
+    fn pointer_speed_scales_movement() {
+        let pointer = Pointer { speed: Some(2.0), acceleration: None };
+        assert_eq!(pointer.accelerate(3.0, -4.0), (6.0, -8.0));
+    }
+
+    #[test]
+    fn pointer_acceleration_keeps_direction() {
+        let pointer = Pointer { speed: None, acceleration: Some(2.0) };
+
+        // A movement of length 5 gets scaled by 5
+        assert_eq!(pointer.accelerate(3.0, -4.0), (15.0, -20.0));
+        // Short ones get slower
+        assert_eq!(pointer.accelerate(0.5, 0.0), (0.25, 0.0));
+    }
+
+    #[test]
+    fn no_pointer_movement_stays_still() {
+        let pointer = Pointer { speed: None, acceleration: Some(0.5) };
+        assert_eq!(pointer.accelerate(0.0, 0.0), (0.0, 0.0));
+        assert_eq!(pointer.accelerate(f64::NAN, 1.0), (0.0, 0.0));
+    }
+}
@@ -0,0 +1,178 @@
+//! Named config profiles.
+//!
+//! Every profile is a complete config file. The default profile keeps using
+//! `config.json`, and every other profile is stored side by side with it as
+//! `config.<name>.json`. Which profile is active (along with any PINs) is
+//! tracked in a small index file, `profiles.json`.
+
+use serde::{Serialize, Deserialize};
+use std::{
+    collections::BTreeMap,
+    path::PathBuf
+};
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+const INDEX_FILE: &str = "profiles.json";
+const CONFIG_PREFIX: &str = "config.";
+const CONFIG_SUFFIX: &str = ".json";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProfileIndex {
+    pub active: String,
+    /// Hashed PINs, keyed by profile name. Profiles without a PIN are absent.
+    #[serde(default)]
+    pub pins: BTreeMap<String, String>,
+}
+
+impl Default for ProfileIndex {
+    fn default() -> Self {
+        ProfileIndex {
+            active: DEFAULT_PROFILE.to_string(),
+            pins: BTreeMap::new()
+        }
+    }
+}
+
+impl ProfileIndex {
+    /// Check a PIN against a profile. Profiles without a PIN accept anything.
+    pub fn check_pin(&self, name: &str, pin: Option<&str>) -> bool {
+        match (self.pins.get(name), pin) {
+            (None, _) => true,
+            (Some(hash), Some(pin)) => *hash == hash_pin(name, pin),
+            (Some(_), None) => false
+        }
+    }
+
+    pub fn set_pin(&mut self, name: &str, pin: Option<&str>) {
+        match pin {
+            Some(pin) => {
+                self.pins.insert(name.to_string(), hash_pin(name, pin));
+            }
+            None => {
+                self.pins.remove(name);
+            }
+        }
+    }
+}
+
+/// Locations of profile files within a config directory.
+#[derive(Clone, Debug)]
+pub struct Profiles {
+    base_path: PathBuf
+}
+
+impl Profiles {
+    pub fn new<P: Into<PathBuf>>(base_path: P) -> Self {
+        Profiles { base_path: base_path.into() }
+    }
+
+    pub fn index_path(&self) -> PathBuf {
+        self.base_path.join(INDEX_FILE)
+    }
+
+    pub fn config_path(&self, name: &str) -> PathBuf {
+        if name == DEFAULT_PROFILE {
+            self.base_path.join("config.json")
+        }
+        else {
+            self.base_path.join(format!("{}{}{}", CONFIG_PREFIX, name, CONFIG_SUFFIX))
+        }
+    }
+
+    /// Path to the config file of the active profile. Falls back to the default
+    /// profile if the active one has gone missing.
+    pub fn active_config_path(&self) -> PathBuf {
+        let index = self.load_index();
+
+        if self.exists(&index.active) {
+            self.config_path(&index.active)
+        }
+        else {
+            self.config_path(DEFAULT_PROFILE)
+        }
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        name == DEFAULT_PROFILE || self.config_path(name).exists()
+    }
+
+    /// List every profile in the config directory, default first.
+    pub fn list(&self) -> Vec<String> {
+        let mut names = vec![DEFAULT_PROFILE.to_string()];
+
+        let entries = match std::fs::read_dir(&self.base_path) {
+            Ok(entries) => entries,
+            Err(err) => {
+                log::error!("Could not list profiles: {}", err);
+                return names;
+            }
+        };
+
+        let mut others = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter_map(|file_name| {
+                file_name
+                    .strip_prefix(CONFIG_PREFIX)?
+                    .strip_suffix(CONFIG_SUFFIX)
+                    .map(|name| name.to_string())
+            })
+            .filter(|name| is_valid_name(name) && name != DEFAULT_PROFILE)
+            .collect::<Vec<_>>();
+        others.sort();
+
+        names.append(&mut others);
+        names
+    }
+
+    pub fn load_index(&self) -> ProfileIndex {
+        use std::fs::read;
+
+        let path = self.index_path();
+        if !path.exists() {
+            return ProfileIndex::default();
+        }
+
+        read(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|bytes| serde_json::from_slice(&bytes).map_err(|err| err.to_string()))
+            .unwrap_or_else(|err| {
+                log::error!("Error while reading profile index: {}", err);
+                ProfileIndex::default()
+            })
+    }
+
+    pub fn save_index(&self, index: &ProfileIndex) -> anyhow::Result<()> {
+        let bytes = serde_json::to_vec_pretty(index)?;
+        std::fs::write(self.index_path(), bytes)?;
+
+        Ok(())
+    }
+}
+
+/// Profile names end up in file names, so they're kept to a conservative set
+/// of characters.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+}
+
+/// PINs are only meant to keep kids out of the grown-up profile, but there's no
+/// reason to store them in plain text either. Salted with the profile name so
+/// identical PINs don't look identical in the index.
+fn hash_pin(name: &str, pin: &str) -> String {
+    use sha2::{Sha256, Digest};
+
+    let mut hasher = Sha256::new();
+    hasher.update(name.as_bytes());
+    hasher.update(b":");
+    hasher.update(pin.as_bytes());
+
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
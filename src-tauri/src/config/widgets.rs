@@ -0,0 +1,122 @@
+//! Props for each home screen widget.
+//!
+//! `Widget::props` is kept as raw JSON so the backend doesn't have to know
+//! about every widget, but these types describe what each widget expects. They
+//! only exist to generate the config schema (and from that, the frontend
+//! types), so they have to be kept in line with the widget components in
+//! `src/routes/home/widgets`.
+
+use schemars::{
+    JsonSchema,
+    gen::SchemaGenerator,
+    schema::{Schema, SchemaObject, SubschemaValidation}
+};
+use serde::{Serialize, Deserialize};
+
+use super::{Coords, Widget};
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum XAlign {
+    Left,
+    Center,
+    Right
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum YAlign {
+    Top,
+    Middle,
+    Bottom
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ClockProps {
+    pub x_align: XAlign,
+    pub y_align: YAlign,
+    /// strftime format string
+    pub format: String
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WeatherProps {
+    pub x_align: XAlign,
+    pub y_align: YAlign,
+    pub heading: WeatherItem,
+    pub subheadings: Vec<WeatherItem>
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum WeatherItem {
+    Current,
+    Forecast3hr,
+    Forecast6hr,
+    Sunset
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioVisualizerProps {
+    pub y_align: YAlign,
+    pub amp: f64
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PomodoroProps {
+    pub x_align: XAlign
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TodoProps {
+    pub x_align: XAlign
+}
+
+/// A widget is one of several shapes, picked by `name`. Each shape gets its own
+/// `props` type; widgets that don't take props accept anything.
+impl JsonSchema for Widget {
+    fn schema_name() -> String {
+        "Widget".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let variants = vec![
+            variant::<ClockProps>(gen, "clock"),
+            variant::<WeatherProps>(gen, "weather"),
+            variant::<AudioVisualizerProps>(gen, "audioVisualizer"),
+            variant::<serde_json::Value>(gen, "player"),
+            variant::<PomodoroProps>(gen, "pomodoro"),
+            variant::<TodoProps>(gen, "todo"),
+        ];
+
+        SchemaObject {
+            subschemas: Some(Box::new(SubschemaValidation {
+                one_of: Some(variants),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }.into()
+    }
+}
+
+fn variant<P: JsonSchema>(gen: &mut SchemaGenerator, name: &str) -> Schema {
+    use serde_json::json;
+
+    let coords = gen.subschema_for::<Coords>();
+    let props = gen.subschema_for::<P>();
+
+    serde_json::from_value(json!({
+        "type": "object",
+        "required": ["name", "coords"],
+        "properties": {
+            "name": { "const": name },
+            "coords": coords,
+            "props": props
+        }
+    })).unwrap()
+}
@@ -0,0 +1,405 @@
+//! Import and export of config bundles.
+//!
+//! A bundle is a zip archive holding everything needed to set up another
+//! machine the same way:
+//! - `manifest.json`, describing what's in the bundle
+//! - `config.json`, the active profile's config
+//! - `images/`, launcher images referenced by `Launcher::image_path`
+//! - `devices/` (optional), the remote server's identity and signing secret, so
+//!   that paired devices keep working on the new machine
+//!
+//! Other secrets (like API keys) are never exported, the config only refers to
+//! them by name.
+//!
+//! Importing reads the whole bundle before touching anything, and only writes
+//! images and the remote server's identity once the resulting config has been
+//! accepted.
+
+use crate::config_listener::ConfigManager;
+use shared::{
+    config::{self, Config},
+    secrets::{self, SecretStore},
+    util::project_dirs::{self, PROJECT_DIRS}
+};
+
+use serde::{Serialize, Deserialize};
+use std::{
+    fs::File,
+    io::{Read, Write},
+    path::{Path, PathBuf}
+};
+use tauri::State;
+use zip::{ZipArchive, ZipWriter, write::FileOptions};
+
+const BUNDLE_VERSION: u32 = 1;
+const DEVICE_FILES: [&str; 2] = ["cert.pem", "key.pem"];
+const JWT_SECRET_FILE: &str = "devices/jwt_secret";
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    includes_devices: bool
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum ImportMode {
+    /// Keep the current config, adding anything from the bundle that's missing.
+    Merge,
+    /// Throw away the current config and use the bundle's.
+    Replace
+}
+
+type BundleError = String;
+
+/// A bundle read into memory, ready to be applied.
+struct Imported {
+    config: Config,
+    /// Launcher images, by where they go
+    images: Vec<(PathBuf, Vec<u8>)>,
+    devices: Option<Devices>
+}
+
+/// The remote server's identity from a bundle.
+struct Devices {
+    /// `DEVICE_FILES`, by name
+    files: Vec<(&'static str, Vec<u8>)>,
+    jwt_secret: Option<String>
+}
+
+#[tauri::command]
+pub fn export_config(
+    config: State<'_, ConfigManager>,
+    path: String,
+    include_devices: bool
+) -> Result<(), BundleError> {
+    let secrets = config.secrets.clone();
+    let config = config.config.read().unwrap().clone();
+
+    export_bundle(config, &secrets, Path::new(&path), include_devices)
+        .map_err(|err| {
+            log::error!("Error while exporting config: {}", err);
+            format!("Could not export config: {}", err)
+        })
+}
+
+/// Import a bundle. The remote server's identity in it (if any) replaces the
+/// current one when replacing the config, but only if `replace_devices` is set
+/// when merging, since that unpairs every device paired with this machine.
+#[tauri::command]
+pub fn import_config(
+    config: State<'_, ConfigManager>,
+    path: String,
+    mode: ImportMode,
+    replace_devices: Option<bool>
+) -> Result<(), BundleError> {
+    config.check_writable()?;
+
+    let log_error = |err: anyhow::Error| {
+        log::error!("Error while importing config: {}", err);
+        format!("Could not import config: {}", err)
+    };
+
+    let imported = import_bundle(Path::new(&path)).map_err(log_error)?;
+
+    let current = config.config.read().unwrap().clone();
+    let new_config = match mode {
+        ImportMode::Merge => merge(current, imported.config),
+        ImportMode::Replace => imported.config
+    };
+    new_config.check().map_err(log_error)?;
+
+    // The images are named after their contents, so writing them can't
+    // clobber any the current launchers use
+    write_images(&imported.images).map_err(log_error)?;
+    config.replace(new_config)?;
+
+    let replace_devices = mode == ImportMode::Replace || replace_devices.unwrap_or(false);
+    match imported.devices {
+        Some(devices) if replace_devices => {
+            write_devices(&config.secrets, devices).map_err(log_error)?;
+            log::info!("Imported remote server identity, restart the remote server to use it");
+        }
+        Some(_) => log::info!("Keeping the current remote server identity"),
+        None => {}
+    }
+
+    Ok(())
+}
+
+fn export_bundle(
+    mut config: Config,
+    secrets: &SecretStore,
+    path: &Path,
+    include_devices: bool
+) -> anyhow::Result<()> {
+    let mut zip = ZipWriter::new(File::create(path)?);
+    let options = FileOptions::default();
+
+    // Images get copied into the bundle, and the config is rewritten to point
+    // at their (relative) location inside it
+    for (i, launcher) in config.launchers.iter_mut().enumerate() {
+        let image_path = match launcher.image_path.clone() {
+            Some(image_path) => image_path,
+            None => continue
+        };
+
+        let bytes = match std::fs::read(&image_path) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                log::warn!("Skipping image {} for \"{}\": {}", image_path, launcher.name, err);
+                continue;
+            }
+        };
+
+        let file_name = Path::new(&image_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "image".to_string());
+        let bundle_path = format!("images/{}_{}", i, file_name);
+
+        zip.start_file(&bundle_path, options)?;
+        zip.write_all(&bytes)?;
+
+        launcher.image_path = Some(bundle_path);
+    }
+
+    if include_devices {
+        for file_name in DEVICE_FILES {
+            let bytes = std::fs::read(project_dirs::config_dir().join(file_name))?;
+
+            zip.start_file(format!("devices/{}", file_name), options)?;
+            zip.write_all(&bytes)?;
+        }
+
+        // Without the signing secret, every paired device would be rejected
+        if let Some(jwt_secret) = secrets.get(secrets::JWT_SECRET) {
+            zip.start_file(JWT_SECRET_FILE, options)?;
+            zip.write_all(jwt_secret.as_bytes())?;
+        }
+    }
+
+    zip.start_file("config.json", options)?;
+    zip.write_all(&serde_json::to_vec_pretty(&config)?)?;
+
+    zip.start_file("manifest.json", options)?;
+    zip.write_all(&serde_json::to_vec_pretty(&Manifest {
+        version: BUNDLE_VERSION,
+        includes_devices: include_devices
+    })?)?;
+
+    zip.finish()?;
+
+    Ok(())
+}
+
+fn import_bundle(path: &Path) -> anyhow::Result<Imported> {
+    let mut zip = ZipArchive::new(File::open(path)?)?;
+
+    let manifest: Manifest = serde_json::from_slice(&read_entry(&mut zip, "manifest.json")?)?;
+    if manifest.version > BUNDLE_VERSION {
+        anyhow::bail!("bundle version {} is newer than this app supports", manifest.version);
+    }
+
+    // Goes through the same upgrade/validation as a config file on disk
+    let mut config = config::parse(&read_entry(&mut zip, "config.json")?)?;
+
+    let image_dir = PROJECT_DIRS.data_dir().join("launcher-images");
+
+    let mut images = vec![];
+    for launcher in config.launchers.iter_mut() {
+        let bundle_path = match launcher.image_path.clone() {
+            Some(bundle_path) => bundle_path,
+            None => continue
+        };
+        if !bundle_path.starts_with("images/") {
+            continue;
+        }
+
+        let bytes = read_entry(&mut zip, &bundle_path)?;
+        let file_name = Path::new(&bundle_path)
+            .file_name()
+            .ok_or_else(|| anyhow::anyhow!("bad image path {}", bundle_path))?;
+        let dest = image_dir.join(image_file_name(&bytes, &file_name.to_string_lossy()));
+
+        launcher.image_path = Some(dest.to_string_lossy().to_string());
+        images.push((dest, bytes));
+    }
+
+    let devices =
+        if manifest.includes_devices {
+            let mut files = vec![];
+            for file_name in DEVICE_FILES {
+                files.push((file_name, read_entry(&mut zip, &format!("devices/{}", file_name))?));
+            }
+
+            let jwt_secret = match read_entry(&mut zip, JWT_SECRET_FILE) {
+                Ok(jwt_secret) => Some(String::from_utf8(jwt_secret)?),
+                Err(_) => None
+            };
+
+            Some(Devices { files, jwt_secret })
+        }
+        else {
+            None
+        };
+
+    Ok(Imported { config, images, devices })
+}
+
+/// Where an imported image goes: its name in the bundle, prefixed with a hash
+/// of its contents so different images never share a file.
+fn image_file_name(bytes: &[u8], file_name: &str) -> String {
+    use sha2::{Sha256, Digest};
+
+    let hash = Sha256::digest(bytes)
+        .iter()
+        .take(8)
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+
+    format!("{}_{}", hash, file_name)
+}
+
+fn write_images(images: &[(PathBuf, Vec<u8>)]) -> anyhow::Result<()> {
+    for (dest, bytes) in images {
+        if let Some(dir) = dest.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(dest, bytes)?;
+    }
+
+    Ok(())
+}
+
+fn write_devices(secrets: &SecretStore, devices: Devices) -> anyhow::Result<()> {
+    // Each file is swapped in whole, so the remote server never reads half of
+    // one. The old ones are kept around in case the import was a mistake
+    for (file_name, bytes) in devices.files {
+        let path = project_dirs::config_dir().join(file_name);
+        let staged = path.with_extension("pem.new");
+
+        std::fs::write(&staged, bytes)?;
+        if path.exists() {
+            std::fs::copy(&path, path.with_extension("pem.bak"))?;
+        }
+        std::fs::rename(&staged, &path)?;
+    }
+
+    if let Some(jwt_secret) = devices.jwt_secret {
+        secrets.set(secrets::JWT_SECRET, &jwt_secret)?;
+    }
+
+    Ok(())
+}
+
+fn read_entry(zip: &mut ZipArchive<File>, name: &str) -> anyhow::Result<Vec<u8>> {
+    let mut entry = zip.by_name(name)?;
+    let mut bytes = vec![];
+    entry.read_to_end(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+/// Merge an imported config into the current one. Local settings win, the
+/// import only fills in what's missing: launchers and macros with new names,
+/// and weather, audio and remote button settings if they aren't configured
+/// here yet.
+fn merge(mut current: Config, imported: Config) -> Config {
+    for launcher in imported.launchers {
+        if !current.launchers.iter().any(|existing| existing.name == launcher.name) {
+            current.launchers.push(launcher);
+        }
+    }
+
+    for m in imported.macros.into_iter().flatten() {
+        let macros = current.macros.get_or_insert_with(Vec::new);
+        if !macros.iter().any(|existing| existing.name == m.name) {
+            macros.push(m);
+        }
+    }
+
+    if current.weather.is_none() {
+        current.weather = imported.weather;
+    }
+    if current.audio_device.is_none() {
+        current.audio_device = imported.audio_device;
+    }
+    if current.shell.is_none() {
+        current.shell = imported.shell;
+    }
+    if current.bindings.is_none() {
+        current.bindings = imported.bindings;
+    }
+
+    current
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::config::Launcher;
+    use serde_json::json;
+
+    fn launcher(name: &str, command: &str) -> Launcher {
+        serde_json::from_value(json!({ "name": name, "command": command, "finder": name })).unwrap()
+    }
+
+    #[test]
+    fn merge_keeps_local_launchers_and_adds_new_ones() {
+        let current = Config {
+            launchers: vec![launcher("a", "local")],
+            shell: Some("zsh".to_string()),
+            ..Config::default()
+        };
+        let imported = Config {
+            launchers: vec![launcher("a", "imported"), launcher("b", "imported")],
+            shell: Some("fish".to_string()),
+            ..Config::default()
+        };
+
+        let merged = merge(current, imported);
+
+        let launchers = merged.launchers
+            .iter()
+            .map(|launcher| (launcher.name.as_str(), launcher.command.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(launchers, vec![("a", "local"), ("b", "imported")]);
+        assert_eq!(merged.shell.as_deref(), Some("zsh"));
+    }
+
+    #[test]
+    fn merge_fills_in_missing_settings() {
+        let current = Config::default();
+
+        let imported = Config {
+            shell: Some("fish".to_string()),
+            ..Config::default()
+        };
+
+        assert_eq!(merge(current, imported).shell.as_deref(), Some("fish"));
+    }
+
+    #[test]
+    fn merged_configs_are_validated() {
+        // An imported macro that's fine to parse but not to run
+        let imported = Config {
+            macros: Some(vec![serde_json::from_value(json!({
+                "name": "wait",
+                "steps": [{ "Delay": { "ms": config::macros::MAX_DELAY_MS + 1 } }]
+            })).unwrap()]),
+            ..Config::default()
+        };
+
+        assert!(merge(Config::default(), imported).check().is_err());
+    }
+
+    #[test]
+    fn image_names_depend_on_contents() {
+        let a = image_file_name(b"one", "icon.png");
+        let b = image_file_name(b"two", "icon.png");
+
+        assert_ne!(a, b);
+        assert_eq!(a, image_file_name(b"one", "icon.png"));
+        assert!(a.ends_with("_icon.png"));
+    }
+}
@@ -6,13 +6,18 @@ use serde::Serialize;
 
 use crate::GlobalAppHandle;
 use shared::{
-    config::{self, Config},
-    util::project_dirs::PROJECT_DIRS
+    config::{self, Config, profile::{self, Profiles, DEFAULT_PROFILE}},
+    secrets::SecretStore,
+    util::project_dirs
 };
 
 pub struct ConfigManager {
     pub config: Arc<RwLock<Config>>,
-    path: PathBuf,
+    pub secrets: Arc<SecretStore>,
+    path: Arc<RwLock<PathBuf>>,
+    profiles: Profiles,
+    read_only: bool,
+    app_handle: GlobalAppHandle,
     _watcher: notify::RecommendedWatcher
 }
 
@@ -29,45 +34,105 @@ impl ConfigEvent {
     }
 }
 
-impl ConfigManager {
-    pub fn new(app_handle: GlobalAppHandle) -> Self {
+#[derive(Clone, Serialize)]
+enum ProfileEvent {
+    Switch {
+        name: String
+    }
+}
 
-        // TODO definitely redundant if this is only ever going to be hardcoded.
-        // Might be nice to let users change this based on environment vars, but
-        // I'm not sure if that's a common usecase
-        let base_path = PROJECT_DIRS.config_dir().into();
-        let path = PROJECT_DIRS.config_dir().join("config.json");
-        log::info!("Config path: {}", path.display());
+impl ProfileEvent {
+    fn channel() -> &'static str {
+        "profile"
+    }
+}
 
-        let config =
-            if !path.exists() {
-                let config = Config::default();
+#[derive(Serialize)]
+pub struct ProfileInfo {
+    name: String,
+    active: bool,
+    has_pin: bool
+}
 
-                config::save_to_path(&path, &config);
+type ProfileError = String;
 
-                config
-            }
-            else {
-                config::load_from_path(&path).unwrap()
-            };
+impl ConfigManager {
+    pub fn new(app_handle: GlobalAppHandle) -> Self {
+        let base_path: PathBuf = project_dirs::config_dir().into();
+        let profiles = Profiles::new(base_path.clone());
+        let path = profiles.active_config_path();
+        let read_only = project_dirs::is_read_only();
+        log::info!("Config path: {}{}", path.display(), if read_only { " (read-only)" } else { "" });
+
+        let secrets = Arc::new(SecretStore::open());
+
+        let mut config = Self::load_or_create(&path, read_only);
+        Self::stash_secrets(&mut config, &secrets, &path, read_only);
         let config = Arc::new(RwLock::new(config));
+        let path = Arc::new(RwLock::new(path));
 
-        let watcher = Self::make_watcher(config.clone(), app_handle, base_path, path.clone());
+        let watcher = Self::make_watcher(
+            config.clone(),
+            secrets.clone(),
+            read_only,
+            app_handle.clone(),
+            base_path,
+            path.clone()
+        );
 
         let config_manager = ConfigManager {
             config,
+            secrets,
             path,
+            profiles,
+            read_only,
+            app_handle,
             _watcher: watcher,
         };
 
         config_manager
     }
 
+    fn load_or_create(path: &PathBuf, read_only: bool) -> Config {
+        if !path.exists() {
+            let config = Config::default();
+
+            if !read_only {
+                config::save_to_path(path, &config);
+            }
+
+            config
+        }
+        else {
+            config::load_from_path(path).unwrap_or_else(|| {
+                // Keep the unreadable file around, since the default config
+                // will replace it the next time anything is saved
+                let backup = path.with_extension("json.bak");
+                match std::fs::copy(path, &backup) {
+                    Ok(_) => log::error!("Using the default config, the unreadable one is backed up at {}", backup.display()),
+                    Err(err) => log::error!("Using the default config, and could not back up the unreadable one: {}", err)
+                }
+
+                Config::default()
+            })
+        }
+    }
+
+    /// Move secrets out of a freshly loaded config, saving it if anything moved.
+    fn stash_secrets(config: &mut Config, secrets: &SecretStore, path: &PathBuf, read_only: bool) {
+        if !read_only && config.stash_secrets(secrets) {
+            log::info!("Moved secrets out of {}", path.display());
+            config::save_to_path(path, config);
+        }
+    }
+
     fn make_watcher(
         config_rw: Arc<RwLock<Config>>,
+        secrets: Arc<SecretStore>,
+        read_only: bool,
         app_handle: GlobalAppHandle,
         base_path: PathBuf,
-        path: PathBuf
+        path_rw: Arc<RwLock<PathBuf>>
     ) -> RecommendedWatcher {
         use notify::{Event, RecursiveMode, Result, Watcher};
 
@@ -76,8 +141,10 @@ impl ConfigManager {
                 if let Ok(event) = res {
                     // TODO only trigger on changes to actual file (path)
                     if event.kind.is_modify() {
-                        if let Some(config) = config::load_from_path(&path) {
+                        let path = path_rw.read().unwrap().clone();
+                        if let Some(mut config) = config::load_from_path(&path) {
                             log::info!("Detected change in config");
+                            Self::stash_secrets(&mut config, &secrets, &path, read_only);
                             *config_rw.write().unwrap() = config.clone();
 
                             app_handle.emit_all(ConfigEvent::channel(), ConfigEvent::Set { config });
@@ -98,7 +165,163 @@ impl ConfigManager {
     }
 
     pub fn save(&self) {
-        config::save_to_path(&self.path, &self.config.read().unwrap());
+        if self.read_only {
+            log::warn!("Config is read-only, changes will not be saved");
+            return;
+        }
+
+        config::save_to_path(&*self.path.read().unwrap(), &self.config.read().unwrap());
+    }
+
+    /// Replace the whole config and save it, unless it's invalid.
+    pub fn replace(&self, mut config: Config) -> Result<(), String> {
+        config.check().map_err(|err| err.to_string())?;
+        for warning in config.lint() {
+            log::warn!("Saving config anyway: {}", warning);
+        }
+
+        if !self.read_only {
+            config.stash_secrets(&self.secrets);
+        }
+
+        *self.config.write().unwrap() = config;
+        self.save();
+
+        Ok(())
+    }
+
+    pub fn list_profiles(&self) -> Vec<ProfileInfo> {
+        let index = self.profiles.load_index();
+
+        self.profiles
+            .list()
+            .into_iter()
+            .map(|name| ProfileInfo {
+                active: name == index.active,
+                has_pin: index.pins.contains_key(&name),
+                name
+            })
+            .collect()
+    }
+
+    pub fn create_profile(&self, name: &str, pin: Option<&str>) -> Result<(), ProfileError> {
+        self.add_profile(name, pin, Config::default())
+    }
+
+    pub fn clone_profile(
+        &self,
+        from: &str,
+        from_pin: Option<&str>,
+        to: &str,
+        to_pin: Option<&str>
+    ) -> Result<(), ProfileError> {
+        self.check_profile(from, from_pin)?;
+
+        let config = config::load_from_path(self.profiles.config_path(from))
+            .ok_or(format!("Could not read profile \"{}\"", from))?;
+
+        self.add_profile(to, to_pin, config)
+    }
+
+    /// Make another profile active, reloading the config and notifying the UI.
+    /// A running remote server picks up the new profile's bindings and macros,
+    /// but keeps its ports and input backend until it's restarted.
+    pub fn switch_profile(&self, name: &str, pin: Option<&str>) -> Result<(), ProfileError> {
+        self.check_profile(name, pin)?;
+
+        let path = self.profiles.config_path(name);
+        let mut config = Self::load_or_create(&path, self.read_only);
+        Self::stash_secrets(&mut config, &self.secrets, &path, self.read_only);
+
+        // In read-only mode, the switch only lasts until the app restarts
+        if !self.read_only {
+            let mut index = self.profiles.load_index();
+            index.active = name.to_string();
+            self.profiles.save_index(&index).map_err(|err| err.to_string())?;
+        }
+
+        log::info!("Switching to profile \"{}\" ({})", name, path.display());
+        *self.path.write().unwrap() = path;
+        *self.config.write().unwrap() = config.clone();
+
+        self.app_handle.emit_all(ProfileEvent::channel(), ProfileEvent::Switch { name: name.to_string() });
+        self.app_handle.emit_all(ConfigEvent::channel(), ConfigEvent::Set { config });
+
+        Ok(())
+    }
+
+    pub fn delete_profile(&self, name: &str, pin: Option<&str>) -> Result<(), ProfileError> {
+        self.check_writable()?;
+        self.check_profile(name, pin)?;
+
+        let mut index = self.profiles.load_index();
+        if name == DEFAULT_PROFILE {
+            return Err("The default profile can't be deleted".to_string());
+        }
+        if name == index.active {
+            return Err("The active profile can't be deleted".to_string());
+        }
+
+        std::fs::remove_file(self.profiles.config_path(name)).map_err(|err| err.to_string())?;
+
+        index.set_pin(name, None);
+        self.profiles.save_index(&index).map_err(|err| err.to_string())
+    }
+
+    /// Set or clear a profile's PIN. Changing an existing PIN needs the old one.
+    pub fn set_profile_pin(
+        &self,
+        name: &str,
+        pin: Option<&str>,
+        new_pin: Option<&str>
+    ) -> Result<(), ProfileError> {
+        self.check_writable()?;
+        self.check_profile(name, pin)?;
+
+        let mut index = self.profiles.load_index();
+        index.set_pin(name, new_pin);
+        self.profiles.save_index(&index).map_err(|err| err.to_string())
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn check_writable(&self) -> Result<(), String> {
+        if self.read_only {
+            return Err("Config is read-only".to_string());
+        }
+
+        Ok(())
+    }
+
+    fn check_profile(&self, name: &str, pin: Option<&str>) -> Result<(), ProfileError> {
+        if !self.profiles.exists(name) {
+            return Err(format!("Could not find profile \"{}\"", name));
+        }
+
+        if !self.profiles.load_index().check_pin(name, pin) {
+            return Err(format!("Wrong PIN for profile \"{}\"", name));
+        }
+
+        Ok(())
+    }
+
+    fn add_profile(&self, name: &str, pin: Option<&str>, config: Config) -> Result<(), ProfileError> {
+        self.check_writable()?;
+
+        if !profile::is_valid_name(name) {
+            return Err(format!("Invalid profile name \"{}\"", name));
+        }
+        if self.profiles.exists(name) {
+            return Err(format!("Profile \"{}\" already exists", name));
+        }
+
+        config::save_to_path(self.profiles.config_path(name), &config);
+
+        let mut index = self.profiles.load_index();
+        index.set_pin(name, pin);
+        self.profiles.save_index(&index).map_err(|err| err.to_string())
     }
 }
 
@@ -108,7 +331,61 @@ pub fn get_config(config: State<'_, ConfigManager>) -> Config {
 }
 
 #[tauri::command]
-pub fn set_config(config: State<'_, ConfigManager>, new_config: Config) {
-    *config.config.write().unwrap() = new_config;
-    config.save();
-}
\ No newline at end of file
+pub fn set_config(config: State<'_, ConfigManager>, new_config: Config) -> Result<(), String> {
+    config.replace(new_config)
+}
+
+#[tauri::command]
+pub fn list_profiles(config: State<'_, ConfigManager>) -> Vec<ProfileInfo> {
+    config.list_profiles()
+}
+
+#[tauri::command]
+pub fn create_profile(
+    config: State<'_, ConfigManager>,
+    name: String,
+    pin: Option<String>
+) -> Result<(), ProfileError> {
+    config.create_profile(&name, pin.as_deref())
+}
+
+#[tauri::command]
+pub fn clone_profile(
+    config: State<'_, ConfigManager>,
+    from: String,
+    from_pin: Option<String>,
+    to: String,
+    to_pin: Option<String>
+) -> Result<(), ProfileError> {
+    config.clone_profile(&from, from_pin.as_deref(), &to, to_pin.as_deref())
+}
+
+/// See `ConfigManager::switch_profile`: the remote server needs a restart to
+/// use the new profile's ports.
+#[tauri::command]
+pub fn switch_profile(
+    config: State<'_, ConfigManager>,
+    name: String,
+    pin: Option<String>
+) -> Result<(), ProfileError> {
+    config.switch_profile(&name, pin.as_deref())
+}
+
+#[tauri::command]
+pub fn delete_profile(
+    config: State<'_, ConfigManager>,
+    name: String,
+    pin: Option<String>
+) -> Result<(), ProfileError> {
+    config.delete_profile(&name, pin.as_deref())
+}
+
+#[tauri::command]
+pub fn set_profile_pin(
+    config: State<'_, ConfigManager>,
+    name: String,
+    pin: Option<String>,
+    new_pin: Option<String>
+) -> Result<(), ProfileError> {
+    config.set_profile_pin(&name, pin.as_deref(), new_pin.as_deref())
+}
@@ -0,0 +1,419 @@
+//! Discovery of installed apps through freedesktop `.desktop` entries.
+//!
+//! Entries are read from the `applications` directory of every XDG data dir
+//! (plus the Flatpak and Snap export dirs, in case they aren't in
+//! `$XDG_DATA_DIRS`), following the Desktop Entry spec closely enough to find
+//! the apps a desktop's own app menu would show.
+
+use crate::{icons, palette};
+use shared::config::Launcher;
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf}
+};
+
+/// The bits of a `[Desktop Entry]` group we care about.
+pub struct DesktopEntry {
+    pub name: String,
+    /// Program followed by its arguments, with field codes already expanded
+    pub exec: Vec<String>,
+    /// Icon name or path, see `icons::resolve`
+    pub icon: Option<String>,
+    pub startup_wm_class: Option<String>
+}
+
+impl DesktopEntry {
+    pub fn to_launcher(&self) -> Launcher {
+        let image_path = self.icon.as_deref().and_then(icons::resolve);
+
+        Launcher {
+            name: self.name.clone(),
+            command: self.exec[0].clone(),
+            finder: self.name.clone(),
+            finder_is_regex: None,
+            finder_app_id: self.startup_wm_class.clone(),
+            finder_process_name: None,
+            finder_exe: None,
+            css_background: image_path.as_deref().and_then(palette::gradient),
+            image_path: image_path.map(|path| path.to_string_lossy().to_string()),
+            args: if self.exec.len() > 1 { Some(self.exec[1..].to_vec()) } else { None },
+            env: None,
+            cwd: None,
+            // The entry has already been split into arguments, a shell would
+            // only get in the way
+            use_shell: Some(false)
+        }
+    }
+}
+
+/// All visible app entries, sorted by name.
+pub fn discover() -> Vec<DesktopEntry> {
+    // Entries are identified by their path relative to `applications`, and the
+    // first data dir to have an ID wins - even if that entry is hidden, which
+    // is how users remove system entries
+    let mut entries = BTreeMap::new();
+    for dir in data_dirs() {
+        let applications = dir.join("applications");
+        for path in find_desktop_files(&applications) {
+            let id = path
+                .strip_prefix(&applications)
+                .unwrap()
+                .to_string_lossy()
+                .replace('/', "-");
+
+            if entries.contains_key(&id) {
+                continue;
+            }
+
+            let entry = std::fs::read_to_string(&path)
+                .ok()
+                .and_then(|contents| parse(&contents, &path));
+            entries.insert(id, entry);
+        }
+    }
+
+    let mut entries = entries
+        .into_values()
+        .flatten()
+        .collect::<Vec<_>>();
+    entries.sort_by_key(|entry| entry.name.to_lowercase());
+
+    entries
+}
+
+/// XDG data dirs in order of precedence.
+fn data_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![];
+
+    if let Some(base_dirs) = directories::BaseDirs::new() {
+        dirs.push(base_dirs.data_dir().to_path_buf());
+        dirs.push(base_dirs.data_dir().join("flatpak/exports/share"));
+    }
+
+    let system_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or("/usr/local/share:/usr/share".to_string());
+    dirs.extend(std::env::split_paths(&system_dirs));
+
+    dirs.push(PathBuf::from("/var/lib/flatpak/exports/share"));
+    dirs.push(PathBuf::from("/var/lib/snapd/desktop"));
+
+    let mut seen = vec![];
+    dirs.retain(|dir| {
+        let new = !seen.contains(dir);
+        seen.push(dir.clone());
+        new
+    });
+
+    dirs
+}
+
+fn find_desktop_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = vec![];
+    collect_desktop_files(dir, &mut BTreeSet::new(), &mut files);
+
+    files
+}
+
+/// Symlinked directories are followed, but only into directories that haven't
+/// been visited yet, so a symlink cycle doesn't recurse forever.
+fn collect_desktop_files(dir: &Path, visited: &mut BTreeSet<PathBuf>, files: &mut Vec<PathBuf>) {
+    let is_new = dir.canonicalize().is_ok_and(|canonical| visited.insert(canonical));
+    if !is_new {
+        return;
+    }
+
+    let read_dir = match std::fs::read_dir(dir) {
+        Ok(read_dir) => read_dir,
+        Err(_) => return
+    };
+
+    for dir_entry in read_dir.flatten() {
+        let path = dir_entry.path();
+        if path.is_dir() {
+            collect_desktop_files(&path, visited, files);
+        }
+        else if path.extension().is_some_and(|extension| extension == "desktop") {
+            files.push(path);
+        }
+    }
+}
+
+/// Parse an entry, returning `None` for anything that shouldn't be shown
+/// (hidden, not an app, or not actually installed).
+fn parse(contents: &str, path: &Path) -> Option<DesktopEntry> {
+    let mut group = BTreeMap::new();
+    let mut in_group = false;
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with('[') {
+            in_group = line == "[Desktop Entry]";
+            continue;
+        }
+
+        // Localized keys (`Name[de]`) are skipped, which leaves the untranslated
+        // values
+        if let (true, Some((key, value))) = (in_group, line.split_once('=')) {
+            let key = key.trim();
+            if !key.contains('[') {
+                group.insert(key.to_string(), unescape(value.trim()));
+            }
+        }
+    }
+
+    let is_true = |key: &str| group.get(key).is_some_and(|value| value == "true");
+
+    if group.get("Type").map(String::as_str) != Some("Application")
+        || is_true("NoDisplay")
+        || is_true("Hidden")
+    {
+        return None;
+    }
+
+    if let Some(try_exec) = group.get("TryExec") {
+        find_executable(try_exec)?;
+    }
+
+    let name = group.get("Name")?.clone();
+    let exec = expand_exec(
+        &split_exec(group.get("Exec")?)?,
+        &name,
+        group.get("Icon").map(String::as_str),
+        path
+    );
+    if exec.is_empty() {
+        return None;
+    }
+
+    Some(DesktopEntry {
+        name,
+        exec,
+        icon: group.get("Icon").cloned(),
+        startup_wm_class: group.get("StartupWMClass").cloned()
+    })
+}
+
+/// Undo the escapes allowed in string values.
+fn unescape(value: &str) -> String {
+    let mut out = String::new();
+    let mut chars = value.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            out.push(ch);
+            continue;
+        }
+
+        match chars.next() {
+            Some('s') => out.push(' '),
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('\\') => out.push('\\'),
+            Some(ch) => {
+                // Left alone, `Exec` has its own layer of escaping on top
+                out.push('\\');
+                out.push(ch);
+            }
+            None => out.push('\\')
+        }
+    }
+
+    out
+}
+
+/// Split an `Exec` value into arguments. Arguments can be double quoted, in
+/// which case `"`, `` ` ``, `$` and `\` are escaped with a backslash.
+fn split_exec(exec: &str) -> Option<Vec<String>> {
+    let mut args = vec![];
+    let mut arg = String::new();
+    let mut in_arg = false;
+    let mut quoted = false;
+    let mut chars = exec.chars();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => {
+                quoted = !quoted;
+                in_arg = true;
+            }
+            '\\' if quoted => arg.push(chars.next()?),
+            ' ' | '\t' if !quoted => {
+                if in_arg {
+                    args.push(std::mem::take(&mut arg));
+                    in_arg = false;
+                }
+            }
+            ch => {
+                arg.push(ch);
+                in_arg = true;
+            }
+        }
+    }
+
+    if quoted {
+        return None;
+    }
+    if in_arg {
+        args.push(arg);
+    }
+
+    Some(args)
+}
+
+/// Expand field codes. There are never any files or URLs to open, so those
+/// codes (and the deprecated ones) just disappear.
+fn expand_exec(args: &[String], name: &str, icon: Option<&str>, path: &Path) -> Vec<String> {
+    let mut expanded = vec![];
+
+    for arg in args {
+        match arg.as_str() {
+            // Codes that expand to a list get removed along with the argument
+            "%f" | "%F" | "%u" | "%U" | "%d" | "%D" | "%n" | "%N" | "%v" | "%m" => continue,
+            "%i" => {
+                if let Some(icon) = icon {
+                    expanded.push("--icon".to_string());
+                    expanded.push(icon.to_string());
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        let mut out = String::new();
+        let mut chars = arg.chars();
+        while let Some(ch) = chars.next() {
+            if ch != '%' {
+                out.push(ch);
+                continue;
+            }
+
+            match chars.next() {
+                Some('%') => out.push('%'),
+                Some('c') => out.push_str(name),
+                Some('k') => out.push_str(&path.to_string_lossy()),
+                _ => {}
+            }
+        }
+
+        expanded.push(out);
+    }
+
+    expanded
+}
+
+/// Find a program the way `TryExec` expects: absolute paths as-is, anything
+/// else on `$PATH`.
+pub fn find_executable(program: &str) -> Option<PathBuf> {
+    let is_executable = |path: &Path| {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            path.metadata().is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        }
+        #[cfg(not(unix))]
+        {
+            path.is_file()
+        }
+    };
+
+    let program = Path::new(program);
+    if program.is_absolute() {
+        return Some(program.to_path_buf()).filter(|path| is_executable(path));
+    }
+
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|dir| dir.join(program))
+        .find(|path| is_executable(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(contents: &str) -> Option<DesktopEntry> {
+        parse(contents, Path::new("/apps/test.desktop"))
+    }
+
+    #[test]
+    fn entries_are_parsed() {
+        let entry = parse_str("\
+            # A comment\n\
+            [Desktop Entry]\n\
+            Type=Application\n\
+            Name=Editor\n\
+            Name[de]=Bearbeiter\n\
+            Icon=editor\n\
+            StartupWMClass=editor-main\n\
+            Exec=editor --new-window %U\n\
+            \n\
+            [Desktop Action new]\n\
+            Name=New Window\n\
+            Exec=editor --other\n\
+        ").unwrap();
+
+        assert_eq!(entry.name, "Editor");
+        assert_eq!(entry.exec, vec!["editor", "--new-window"]);
+        assert_eq!(entry.icon.as_deref(), Some("editor"));
+        assert_eq!(entry.startup_wm_class.as_deref(), Some("editor-main"));
+    }
+
+    #[test]
+    fn hidden_entries_and_non_apps_are_skipped() {
+        let app = "[Desktop Entry]\nType=Application\nName=App\nExec=app\n";
+
+        assert!(parse_str(app).is_some());
+        assert!(parse_str(&format!("{}NoDisplay=true\n", app)).is_none());
+        assert!(parse_str(&format!("{}Hidden=true\n", app)).is_none());
+        assert!(parse_str(&format!("{}TryExec=/nonexistent/app\n", app)).is_none());
+        assert!(parse_str("[Desktop Entry]\nType=Link\nName=Site\nURL=https://example.com\n").is_none());
+        assert!(parse_str("[Desktop Entry]\nType=Application\nName=App\n").is_none());
+    }
+
+    #[test]
+    fn exec_arguments_are_split_and_unquoted() {
+        assert_eq!(
+            split_exec(r#"app "two words" "a \"quote\"" plain"#).unwrap(),
+            vec!["app", "two words", "a \"quote\"", "plain"]
+        );
+        assert_eq!(split_exec(r#"app """#).unwrap(), vec!["app", ""]);
+        assert!(split_exec(r#"app "unclosed"#).is_none());
+    }
+
+    #[test]
+    fn field_codes_are_expanded() {
+        let args = ["app", "%F", "%i", "--name=%c", "%k", "100%%"].map(String::from);
+
+        assert_eq!(
+            expand_exec(&args, "App", Some("app-icon"), Path::new("/apps/app.desktop")),
+            vec!["app", "--icon", "app-icon", "--name=App", "/apps/app.desktop", "100%"]
+        );
+        assert_eq!(expand_exec(&args[..3], "App", None, Path::new("/apps/app.desktop")), vec!["app"]);
+    }
+
+    #[test]
+    fn string_values_are_unescaped() {
+        assert_eq!(unescape(r"a\sb\\c\$d"), r"a b\c\$d");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_cycles_are_only_visited_once() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("sub/app.desktop"), "").unwrap();
+        std::os::unix::fs::symlink(dir.path(), dir.path().join("sub/loop")).unwrap();
+
+        assert_eq!(find_desktop_files(dir.path()), vec![dir.path().join("sub/app.desktop")]);
+    }
+}
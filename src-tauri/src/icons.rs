@@ -0,0 +1,181 @@
+//! Launcher images from the freedesktop icon theme.
+//!
+//! Icons are referenced where they're installed rather than copied, the webview
+//! reaches them through Tauri's asset protocol (see `image_path`).
+
+use crate::{
+    config_listener::ConfigManager,
+    desktop_entries::{self, DesktopEntry},
+    palette
+};
+use shared::config::Launcher;
+
+#[cfg(unix)]
+use lazy_static::lazy_static;
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    sync::Mutex
+};
+use tauri::State;
+
+/// Launcher tiles are big, so go for the biggest size most themes have
+#[cfg(unix)]
+const ICON_SIZE: u16 = 256;
+
+/// Launchers `fill_launcher_icons` already looked for, by name and command.
+/// Those it found nothing for are left alone until the app restarts, instead
+/// of rescanning every time the launcher screen loads.
+static TRIED: Mutex<BTreeSet<(String, String)>> = Mutex::new(BTreeSet::new());
+
+#[cfg(unix)]
+lazy_static! {
+    static ref THEME: String = freedesktop_icons::default_theme_gtk()
+        .unwrap_or("hicolor".to_string());
+}
+
+/// Resolve an `Icon=` value: absolute paths are used as-is, names are looked up
+/// in the current icon theme (and its parents, then hicolor, then pixmaps).
+pub fn resolve(icon: &str) -> Option<PathBuf> {
+    let path = Path::new(icon);
+    if path.is_absolute() {
+        return Some(path.to_path_buf()).filter(|path| path.is_file());
+    }
+
+    // Names aren't supposed to have extensions, but some entries have them
+    // anyway
+    let name = icon
+        .strip_suffix(".png")
+        .or(icon.strip_suffix(".svg"))
+        .or(icon.strip_suffix(".xpm"))
+        .unwrap_or(icon);
+
+    #[cfg(unix)]
+    {
+        freedesktop_icons::lookup(name)
+            .with_theme(&THEME)
+            .with_size(ICON_SIZE)
+            .with_cache()
+            .find()
+            // Webviews can't show XPMs
+            .filter(|path| path.extension().is_some_and(|extension| extension != "xpm"))
+    }
+    #[cfg(not(unix))]
+    {
+        // TODO no icon themes here
+        let _ = name;
+        None
+    }
+}
+
+/// Find an icon for a launcher: the icon of the installed app it matches (by
+/// command, then by name), or failing that, an icon named after the program.
+pub fn guess(launcher: &Launcher, entries: &[DesktopEntry]) -> Option<PathBuf> {
+    let exec = launcher_exec(launcher);
+
+    let entry = entries
+        .iter()
+        .find(|entry| entry.exec == exec)
+        .or_else(|| entries.iter().find(|entry| entry.name.eq_ignore_ascii_case(&launcher.name)));
+
+    if let Some(icon) = entry.and_then(|entry| entry.icon.as_deref()).and_then(resolve) {
+        return Some(icon);
+    }
+
+    exec.first()
+        .and_then(|program| Path::new(program).file_name())
+        .and_then(|program| resolve(&program.to_string_lossy()))
+}
+
+/// A launcher's command split into arguments, close enough for comparing with
+/// desktop entries.
+fn launcher_exec(launcher: &Launcher) -> Vec<String> {
+    let mut exec =
+        if launcher.use_shell.unwrap_or(true) {
+            launcher.command.split_whitespace().map(|arg| arg.to_string()).collect()
+        }
+        else {
+            vec![launcher.command.clone()]
+        };
+
+    exec.extend(launcher.args.iter().flatten().cloned());
+
+    exec
+}
+
+/// Fill in `image_path` for every launcher that doesn't have one yet, and a
+/// `css_background` to go with it. Returns how many launchers changed.
+///
+/// The frontend runs this whenever it loads, so launchers added to the config
+/// by hand get their icons too. Does nothing with a read-only config.
+#[tauri::command]
+pub async fn fill_launcher_icons(config: State<'_, ConfigManager>) -> Result<usize, String> {
+    if config.is_read_only() {
+        return Ok(0);
+    }
+
+    let mut launchers = config.config.read().unwrap().launchers.clone();
+    { // Lock for TRIED
+        let mut tried = TRIED.lock().unwrap();
+        launchers.retain(|launcher| {
+            (launcher.image_path.is_none() || launcher.css_background.is_none())
+                && tried.insert((launcher.name.clone(), launcher.command.clone()))
+        });
+    }
+    if launchers.is_empty() {
+        return Ok(0);
+    }
+
+    // Scanning desktop entries and decoding images takes a while
+    let launchers = tokio::task::spawn_blocking(move || {
+        fill(&mut launchers);
+        launchers
+    })
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // The config could have changed in the meantime, so only fill in what's
+    // still missing
+    let mut new_config = config.config.read().unwrap().clone();
+    let mut count = 0;
+    for launcher in new_config.launchers.iter_mut() {
+        let Some(filled) = launchers.iter().find(|filled| filled.name == launcher.name) else {
+            continue;
+        };
+
+        let mut changed = false;
+        if launcher.image_path.is_none() && filled.image_path.is_some() {
+            launcher.image_path = filled.image_path.clone();
+            changed = true;
+        }
+        if launcher.css_background.is_none() && filled.css_background.is_some() && launcher.image_path == filled.image_path {
+            launcher.css_background = filled.css_background.clone();
+            changed = true;
+        }
+
+        if changed {
+            count += 1;
+        }
+    }
+
+    if count > 0 {
+        config.replace(new_config)?;
+    }
+
+    Ok(count)
+}
+
+fn fill(launchers: &mut [Launcher]) {
+    let entries = desktop_entries::discover();
+
+    for launcher in launchers.iter_mut() {
+        if launcher.image_path.is_none() {
+            launcher.image_path = guess(launcher, &entries)
+                .map(|icon| icon.to_string_lossy().to_string());
+        }
+
+        if let (None, Some(image_path)) = (&launcher.css_background, &launcher.image_path) {
+            launcher.css_background = palette::gradient(Path::new(image_path));
+        }
+    }
+}
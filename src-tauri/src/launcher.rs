@@ -1,57 +1,456 @@
-use crate::config_listener::ConfigManager;
+use crate::{
+    GlobalAppHandle,
+    config_listener::ConfigManager,
+    launcher_log::{self, Capture, LauncherLog},
+    remote_server,
+    window_manager::{WindowManager, WindowQuery}
+};
 
+use serde::Serialize;
+use shared::config::{Config, ForegroundOnHome, Launcher};
 use tauri::State;
 use std::{
-    collections::BTreeMap,
-    process::Child,
-    sync::Mutex
+    collections::{BTreeMap, BTreeSet},
+    process::{Child, ExitStatus},
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, SystemTime}
 };
 
+/// How long a launcher gets to exit after being asked nicely, before it gets
+/// killed.
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a killed launcher gets to actually go away.
+const KILL_TIMEOUT: Duration = Duration::from_secs(2);
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 pub struct LauncherManager {
     fallback_shell: String,
-    // TODO this doesn't do much
-    children: Mutex<BTreeMap<String, Child>>
+    supervisor: Supervisor,
+    window_manager: WindowManager,
+    foreground: Foreground,
+    /// Launchers paused by going home, to be resumed when launched again
+    paused: Mutex<BTreeSet<String>>,
+    /// Open logs by launcher name, shared by every process a launcher starts
+    logs: Mutex<BTreeMap<String, LauncherLog>>
 }
 
 impl LauncherManager {
-    pub fn new() -> Self {
+    pub fn new(app_handle: GlobalAppHandle, config: &ConfigManager) -> Self {
+        let foreground = Foreground {
+            name: Arc::new(Mutex::new(None)),
+            config: config.config.clone()
+        };
+
         LauncherManager {
             fallback_shell: "sh".to_string(),
-            children: Mutex::new(BTreeMap::new())
+            supervisor: Supervisor::new(app_handle, foreground.clone()),
+            window_manager: WindowManager::detect(),
+            foreground,
+            paused: Mutex::new(BTreeSet::new()),
+            logs: Mutex::new(BTreeMap::new())
         }
     }
-}
 
-// Bring already-running processes to the foreground
-fn raise(finder: &str, is_regex: bool) -> bool {
-    #[cfg(unix)]
-    {
-        let windows = wmctrl::get_windows();
+    /// A launcher's log, opening it if this is its first process.
+    fn log(&self, name: &str) -> std::io::Result<LauncherLog> {
+        let mut logs = self.logs.lock().unwrap();
+
+        if let Some(log) = logs.get(name) {
+            return Ok(log.clone());
+        }
+
+        let log = LauncherLog::open(name)?;
+        logs.insert(name.to_string(), log.clone());
+
+        Ok(log)
+    }
+
+    /// Bring an already-running launcher to the foreground, returning whether
+    /// it was running.
+    fn raise(&self, launcher: &Launcher) -> bool {
+        let query = self.query(launcher);
 
-        let window =
-            if is_regex {
-                wmctrl::utils::find_window_by_regexp(&windows, finder)
+        match self.window_manager.raise(&query) {
+            // No window could just mean it hasn't opened one yet (or it's on
+            // a display no backend can see), which is still running
+            Ok(found) => found || query.has_processes(),
+            Err(err) => {
+                // Can't look at windows, but at least don't start a second
+                // copy of something that's still running
+                log::error!("Could not look for windows: {}", err);
+                query.has_processes()
+            }
+        }
+    }
+
+    /// Which windows belong to a launcher. Windows of processes the launcher
+    /// started always count, on top of whatever its finders match.
+    fn query(&self, launcher: &Launcher) -> WindowQuery {
+        let has_finders = launcher.finder_app_id.is_some()
+            || launcher.finder_process_name.is_some()
+            || launcher.finder_exe.is_some();
+
+        // Titles are a last resort, they change all the time (browser tabs)
+        let mut query =
+            if has_finders {
+                WindowQuery::default()
             }
             else {
-                wmctrl::utils::find_window_by_title(&windows, finder)
+                WindowQuery::for_finder(&launcher.finder, launcher.finder_is_regex.unwrap_or(false))
             };
 
-        if let Some(window) = window {
-            window.raise();
-            return true;
+        if let Some(app_id) = &launcher.finder_app_id {
+            query = query.with_app_id(app_id);
         }
+        query = query.with_processes(launcher.finder_process_name.as_deref(), launcher.finder_exe.as_deref());
+        if let Some(pid) = self.supervisor.running_pid(&launcher.name) {
+            query = query.with_process_group(pid);
+        }
+
+        query
     }
-    #[cfg(windows)]
-    {
-        // TODO
+
+    /// Get the foreground launcher out of the way of the home screen, as
+    /// configured by `foreground_on_home`. Blocks on the window manager.
+    pub fn home(&self, config: &ConfigManager) {
+        let name = match self.foreground.get() {
+            Some(name) => name,
+            None => return
+        };
+        self.foreground.set(None);
+
+        let (launcher, foreground_on_home) = { // Lock for config
+            let config = config.config.read().unwrap();
+
+            let launcher = config.launchers
+                .iter()
+                .find(|launcher| launcher.name == name)
+                .cloned();
+
+            (launcher, config.home.foreground_on_home.unwrap_or(ForegroundOnHome::Keep))
+        };
+
+        let launcher = match (foreground_on_home, launcher) {
+            (ForegroundOnHome::Keep, _) => return,
+            (_, Some(launcher)) => launcher,
+            (_, None) => return
+        };
+
+        // The user could have switched to something else since launching it,
+        // which shouldn't get minimized or paused from under them
+        let query = self.query(&launcher);
+        match self.window_manager.is_active(&query) {
+            Ok(true) => {}
+            Ok(false) => {
+                log::info!("Launcher \"{}\" isn't the active window anymore, leaving it be", name);
+                return;
+            }
+            // Can't tell, go by what was last launched
+            Err(err) => log::error!("Could not check the active window: {}", err)
+        }
+
+        match foreground_on_home {
+            ForegroundOnHome::Minimize => {
+                if let Err(err) = self.window_manager.minimize(&query) {
+                    log::error!("Could not minimize \"{}\": {}", name, err);
+                }
+            }
+            ForegroundOnHome::Pause => {
+                if let Some(pid) = self.supervisor.running_pid(&name) {
+                    log::info!("Pausing launcher \"{}\" ({})", name, pid);
+                    match signal::pause(pid) {
+                        Ok(()) => {
+                            self.paused.lock().unwrap().insert(name);
+                        }
+                        Err(err) => log::error!("Could not pause \"{}\": {}", name, err)
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Pass the foreground launcher on to the remote server again, for when
+    /// it (re)starts and doesn't know it yet.
+    pub fn send_foreground(&self) {
+        self.foreground.send(self.foreground.get());
+    }
+
+    /// Resume a launcher if it was paused by going home.
+    fn resume(&self, name: &str) {
+        if !self.paused.lock().unwrap().remove(name) {
+            return;
+        }
+
+        if let Some(pid) = self.supervisor.running_pid(name) {
+            log::info!("Resuming launcher \"{}\" ({})", name, pid);
+            if let Err(err) = signal::resume(pid) {
+                log::error!("Could not resume \"{}\": {}", name, err);
+            }
+        }
+    }
+}
+
+/// The launcher last launched or raised, which is presumably on screen. The
+/// remote server is kept up to date so it can use that launcher's bindings.
+#[derive(Clone)]
+struct Foreground {
+    name: Arc<Mutex<Option<String>>>,
+    /// For the remote server's port
+    config: Arc<RwLock<Config>>
+}
+
+impl Foreground {
+    fn get(&self) -> Option<String> {
+        self.name.lock().unwrap().clone()
+    }
+
+    fn set(&self, name: Option<String>) {
+        *self.name.lock().unwrap() = name.clone();
+        self.send(name);
+    }
+
+    /// Clear the foreground if it's `name`, which isn't running anymore.
+    fn clear(&self, name: &str) {
+        { // Lock for name
+            let mut current = self.name.lock().unwrap();
+            if current.as_deref() != Some(name) {
+                return;
+            }
+            *current = None;
+        }
+
+        self.send(None);
+    }
+
+    fn send(&self, name: Option<String>) {
+        let port_internal = self.config.read().unwrap().remote_server.port_internal;
+        tauri::async_runtime::spawn(remote_server::set_foreground(port_internal, name));
+    }
+}
+
+/// Keeps track of every process started by a launcher. Each child gets a
+/// thread that waits on it, so exited processes get reaped right away (instead
+/// of hanging around as zombies) and the UI hears about it.
+#[derive(Clone)]
+struct Supervisor {
+    statuses: Arc<Mutex<BTreeMap<String, LauncherStatus>>>,
+    app_handle: GlobalAppHandle,
+    foreground: Foreground
+}
+
+#[derive(Clone, Serialize)]
+pub struct LauncherStatus {
+    name: String,
+    pid: u32,
+    /// Milliseconds since the Unix epoch
+    started: u64,
+    state: LauncherState
+}
+
+#[derive(Clone, Serialize)]
+pub enum LauncherState {
+    Running,
+    Exited {
+        /// Exit code, if the process exited normally
+        code: Option<i32>,
+        /// Signal that killed the process, if any (Unix only)
+        signal: Option<i32>
+    }
+}
+
+#[derive(Clone, Serialize)]
+enum LauncherEvent {
+    State {
+        status: LauncherStatus
+    }
+}
+
+impl LauncherEvent {
+    fn channel() -> &'static str {
+        "launcher"
+    }
+}
+
+impl Supervisor {
+    fn new(app_handle: GlobalAppHandle, foreground: Foreground) -> Self {
+        Supervisor {
+            statuses: Arc::new(Mutex::new(BTreeMap::new())),
+            app_handle,
+            foreground
+        }
+    }
+
+    /// Start tracking a freshly spawned child, along with its log and the
+    /// capture of its output if it has one.
+    fn track(&self, name: String, mut child: Child, log: Option<(LauncherLog, Capture)>) {
+        let status = LauncherStatus {
+            name: name.clone(),
+            pid: child.id(),
+            started: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|duration| duration.as_millis() as u64)
+                .unwrap_or(0),
+            state: LauncherState::Running
+        };
+
+        { // Lock for statuses
+            let mut statuses = self.statuses.lock().unwrap();
+            statuses.insert(name.clone(), status.clone());
+        }
+        self.emit(status);
+
+        let supervisor = self.clone();
+        std::thread::spawn(move || {
+            let state = match child.wait() {
+                Ok(exit_status) => exited(exit_status),
+                Err(err) => {
+                    log::error!("Error while waiting on \"{}\": {}", name, err);
+                    LauncherState::Exited { code: None, signal: None }
+                }
+            };
+
+            if let (Some((log, capture)), LauncherState::Exited { code, signal }) = (log, &state) {
+                capture.drain();
+                log.line(&format!("--- Exited (code {:?}, signal {:?}) ---", code, signal));
+            }
+
+            supervisor.update(&name, child.id(), state);
+        });
+    }
+
+    fn update(&self, name: &str, pid: u32, state: LauncherState) {
+        let status = { // Lock for statuses
+            let mut statuses = self.statuses.lock().unwrap();
+
+            // The launcher may have been started again in the meantime, in
+            // which case this update is about an old process
+            match statuses.get_mut(name) {
+                Some(status) if status.pid == pid => {
+                    status.state = state;
+                    status.clone()
+                }
+                _ => return
+            }
+        };
+
+        log::info!("Launcher \"{}\" ({}) exited", name, pid);
+        self.foreground.clear(name);
+        self.emit(status);
+    }
+
+    fn emit(&self, status: LauncherStatus) {
+        self.app_handle.emit_all(LauncherEvent::channel(), LauncherEvent::State { status });
+    }
+
+    fn running_pid(&self, name: &str) -> Option<u32> {
+        let statuses = self.statuses.lock().unwrap();
+
+        statuses
+            .get(name)
+            .filter(|status| matches!(status.state, LauncherState::Running))
+            .map(|status| status.pid)
+    }
+
+    fn running(&self) -> Vec<LauncherStatus> {
+        let statuses = self.statuses.lock().unwrap();
+
+        statuses
+            .values()
+            .filter(|status| matches!(status.state, LauncherState::Running))
+            .cloned()
+            .collect()
+    }
+}
+
+fn exited(exit_status: ExitStatus) -> LauncherState {
+    #[cfg(unix)]
+    let signal = {
+        use std::os::unix::process::ExitStatusExt;
+        exit_status.signal()
+    };
+    #[cfg(not(unix))]
+    let signal = None;
+
+    LauncherState::Exited {
+        code: exit_status.code(),
+        signal
+    }
+}
+
+/// Signalling for launched processes. Launchers are started in their own
+/// process group (the command runs through `sh -c`, and apps love to spawn
+/// helpers), so signals go to the whole group.
+#[cfg(unix)]
+mod signal {
+    pub fn terminate(pgid: u32) -> std::io::Result<()> {
+        send(pgid, libc::SIGTERM)
+    }
+
+    pub fn kill(pgid: u32) -> std::io::Result<()> {
+        send(pgid, libc::SIGKILL)
+    }
+
+    pub fn pause(pgid: u32) -> std::io::Result<()> {
+        send(pgid, libc::SIGSTOP)
+    }
+
+    pub fn resume(pgid: u32) -> std::io::Result<()> {
+        send(pgid, libc::SIGCONT)
+    }
+
+    /// Whether any process in the group is still around.
+    pub fn is_alive(pgid: u32) -> bool {
+        send(pgid, 0).is_ok()
+    }
+
+    fn send(pgid: u32, signal: libc::c_int) -> std::io::Result<()> {
+        // Negative pid = the whole process group
+        match unsafe { libc::kill(-(pgid as libc::pid_t), signal) } {
+            0 => Ok(()),
+            _ => Err(std::io::Error::last_os_error())
+        }
     }
-    
-    return false;
 }
 
-pub fn raise_home() {
-    // TODO lol more hardcoding
-    raise("^Macropinna$", true);
+#[cfg(windows)]
+mod signal {
+    use std::process::Command;
+
+    // No process groups or signals here, `taskkill /T` takes down the tree
+    pub fn terminate(pid: u32) -> std::io::Result<()> {
+        taskkill(pid, false)
+    }
+
+    pub fn kill(pid: u32) -> std::io::Result<()> {
+        taskkill(pid, true)
+    }
+
+    // TODO suspending processes takes undocumented APIs here
+    pub fn pause(_pid: u32) -> std::io::Result<()> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+
+    pub fn resume(_pid: u32) -> std::io::Result<()> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+
+    pub fn is_alive(pid: u32) -> bool {
+        Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+            .output()
+            .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+    }
+
+    fn taskkill(pid: u32, force: bool) -> std::io::Result<()> {
+        let pid = pid.to_string();
+        let mut args = vec!["/PID", &pid, "/T"];
+        if force {
+            args.push("/F");
+        }
+
+        Command::new("taskkill").args(args).output().map(|_| ())
+    }
 }
 
 type LaunchError = String;
@@ -62,7 +461,47 @@ pub fn launch(
     config: State<'_, ConfigManager>,
     name: String
 ) -> Result<(), LaunchError> {
-    use std::process::Command;
+    start(&manager, &config, name)
+}
+
+/// Stop a launched app: ask it to exit, then kill it if it hasn't after
+/// `STOP_TIMEOUT`.
+#[tauri::command]
+pub async fn stop_launcher(
+    manager: State<'_, LauncherManager>,
+    name: String
+) -> Result<(), LaunchError> {
+    stop(&manager, &name).await
+}
+
+#[tauri::command]
+pub async fn restart_launcher(
+    manager: State<'_, LauncherManager>,
+    config: State<'_, ConfigManager>,
+    name: String
+) -> Result<(), LaunchError> {
+    stop(&manager, &name).await?;
+    start(&manager, &config, name)
+}
+
+#[tauri::command]
+pub fn list_running(manager: State<'_, LauncherManager>) -> Vec<LauncherStatus> {
+    manager.supervisor.running()
+}
+
+/// The last `lines` lines of output from a launcher's processes.
+#[tauri::command]
+pub fn get_launcher_log(name: String, lines: Option<usize>) -> Result<Vec<String>, LaunchError> {
+    launcher_log::tail(&name, lines.unwrap_or(launcher_log::DEFAULT_TAIL_LINES))
+        .map_err(|err| format!("Could not read log: {}", err))
+}
+
+pub fn start(
+    manager: &LauncherManager,
+    config: &ConfigManager,
+    name: String
+) -> Result<(), LaunchError> {
+    use std::process::{Command, Stdio};
 
     let (launcher, shell) = { // Lock for config
         let config = config.config.read().unwrap();
@@ -80,21 +519,119 @@ pub fn launch(
         (launcher, shell)
     };
 
-    if raise(&launcher.finder, launcher.finder_is_regex.unwrap_or(false)) {
+    manager.resume(&name);
+
+    if manager.raise(&launcher) {
+        manager.foreground.set(Some(name));
         return Ok(());
     }
 
-    let child = Command::new(&shell)
-        // TODO this is hardcoded to work with bash/sh, not sure how this goes
-        // with things like PowerShell
-        .args(["-c", &launcher.command])
+    let args = launcher.args.clone().unwrap_or_default();
+
+    let mut command =
+        if launcher.use_shell.unwrap_or(true) {
+            let mut command = Command::new(&shell);
+            // TODO this is hardcoded to work with bash/sh, not sure how this
+            // goes with things like PowerShell
+            if args.is_empty() {
+                command.args(["-c", &launcher.command]);
+            }
+            else {
+                // Let the shell do the quoting: extra args become "$@" (with
+                // the shell itself as $0)
+                command
+                    .args(["-c", &format!("{} \"$@\"", launcher.command), &shell])
+                    .args(&args);
+            }
+            command
+        }
+        else {
+            let mut command = Command::new(&launcher.command);
+            command.args(&args);
+            command
+        };
+
+    if let Some(env) = &launcher.env {
+        command.envs(env);
+    }
+    if let Some(cwd) = &launcher.cwd {
+        command.current_dir(cwd);
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    // Output goes to the launcher's log if possible, otherwise it's just
+    // inherited like before
+    let log = manager.log(&name)
+        .map_err(|err| {
+            log::error!("Could not open log for \"{}\": {}", name, err);
+        })
+        .ok();
+    if log.is_some() {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+
+    let mut child = command
         .spawn()
         .map_err(|err| format!("Could not launch: {}", err))?;
 
-    { // Lock for manager.children
-        let mut children = manager.children.lock().unwrap();
-        children.insert(name, child);
+    let log = log.map(|log| {
+        log.line(&format!("--- Started \"{}\" (pid {}) ---", launcher.command, child.id()));
+        let capture = log.capture(&mut child);
+        (log, capture)
+    });
+
+    manager.supervisor.track(name.clone(), child, log);
+    manager.foreground.set(Some(name));
+
+    Ok(())
+}
+
+async fn stop(manager: &LauncherManager, name: &str) -> Result<(), LaunchError> {
+    let pid = manager.supervisor
+        .running_pid(name)
+        .ok_or(format!("Launcher \"{}\" is not running", name))?;
+
+    // A paused process can't act on being asked to exit
+    manager.resume(name);
+
+    log::info!("Stopping launcher \"{}\" ({})", name, pid);
+    // Its bindings shouldn't apply while it's going away (or restarting)
+    manager.foreground.clear(name);
+    signal::terminate(pid).map_err(|err| format!("Could not stop: {}", err))?;
+
+    if wait_for_exit(pid, STOP_TIMEOUT).await {
+        return Ok(());
+    }
+
+    log::info!("Launcher \"{}\" ({}) didn't exit in time, killing it", name, pid);
+    signal::kill(pid).map_err(|err| format!("Could not kill: {}", err))?;
+
+    // Make sure it's gone before returning, so a restart doesn't find (and
+    // raise) the dying window instead of starting a new process
+    if !wait_for_exit(pid, KILL_TIMEOUT).await {
+        return Err(format!("Launcher \"{}\" is still running after being killed", name));
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Poll until `pid` is gone, returning whether it went within `timeout`.
+async fn wait_for_exit(pid: u32, timeout: Duration) -> bool {
+    use tokio::time::{sleep, Instant};
+
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if !signal::is_alive(pid) {
+            return true;
+        }
+
+        sleep(STOP_POLL_INTERVAL).await;
+    }
+
+    !signal::is_alive(pid)
+}
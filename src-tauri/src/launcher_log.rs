@@ -0,0 +1,243 @@
+//! Logs of whatever launched apps print.
+//!
+//! Each launcher gets its own log file in `<data dir>/logs`, holding both
+//! stdout and stderr of every process it started. Logs are rotated once they
+//! get too big, keeping a few old files around. There should only be one
+//! `LauncherLog` per launcher at a time (see `LauncherManager`), since each one
+//! keeps track of its file's size for rotating.
+
+use shared::util::project_dirs::PROJECT_DIRS;
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    process::Child,
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+    time::{Duration, Instant}
+};
+
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+const MAX_ROTATIONS: usize = 3;
+pub const DEFAULT_TAIL_LINES: usize = 200;
+
+/// How long to wait for the rest of a process's output once it exits. Helpers
+/// it left running can hold the pipes open indefinitely.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Handle to a launcher's log. Cloning gives another handle to the same file.
+#[derive(Clone)]
+pub struct LauncherLog {
+    inner: Arc<Mutex<Inner>>
+}
+
+struct Inner {
+    path: PathBuf,
+    file: File,
+    size: u64
+}
+
+impl LauncherLog {
+    pub fn open(name: &str) -> std::io::Result<Self> {
+        let path = log_path(name);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(LauncherLog {
+            inner: Arc::new(Mutex::new(Inner { path, file, size }))
+        })
+    }
+
+    /// Append a line to the log, rotating it first if it's full.
+    pub fn line(&self, line: &str) {
+        let mut inner = self.inner.lock().unwrap();
+
+        if inner.size >= MAX_LOG_SIZE {
+            if let Err(err) = inner.rotate() {
+                log::error!("Could not rotate {}: {}", inner.path.display(), err);
+            }
+        }
+
+        let line = format!("{}\n", line);
+        match inner.file.write_all(line.as_bytes()) {
+            Ok(()) => inner.size += line.len() as u64,
+            Err(err) => log::error!("Could not write to {}: {}", inner.path.display(), err)
+        }
+    }
+
+    /// Take over a child's (piped) stdout and stderr, copying them into the
+    /// log from a pair of threads that live as long as the pipes do.
+    pub fn capture(&self, child: &mut Child) -> Capture {
+        let mut threads = vec![];
+        if let Some(stdout) = child.stdout.take() {
+            threads.push(self.copy_from(stdout, "out"));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            threads.push(self.copy_from(stderr, "err"));
+        }
+
+        Capture { threads }
+    }
+
+    fn copy_from<R: Read + Send + 'static>(&self, stream: R, tag: &'static str) -> JoinHandle<()> {
+        let log = self.clone();
+
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(stream);
+            let mut buf = vec![];
+
+            // Apps don't always print valid UTF-8, so go by bytes
+            while let Ok(n) = reader.read_until(b'\n', &mut buf) {
+                if n == 0 {
+                    break;
+                }
+
+                let line = String::from_utf8_lossy(&buf);
+                log.line(&format!("[{}] {}", tag, line.trim_end_matches(['\n', '\r'])));
+                buf.clear();
+            }
+        })
+    }
+}
+
+/// The threads copying a process's output into its log.
+pub struct Capture {
+    threads: Vec<JoinHandle<()>>
+}
+
+impl Capture {
+    /// Wait (up to `DRAIN_TIMEOUT`) for the copying to finish, so nothing the
+    /// process printed ends up after whatever gets logged next.
+    pub fn drain(self) {
+        let deadline = Instant::now() + DRAIN_TIMEOUT;
+        while !self.threads.iter().all(|thread| thread.is_finished()) {
+            if Instant::now() >= deadline {
+                return;
+            }
+
+            std::thread::sleep(DRAIN_POLL_INTERVAL);
+        }
+    }
+}
+
+impl Inner {
+    /// `<name>.log` becomes `<name>.log.1`, `<name>.log.1` becomes
+    /// `<name>.log.2`, and so on, dropping the oldest.
+    fn rotate(&mut self) -> std::io::Result<()> {
+        for i in (1..MAX_ROTATIONS).rev() {
+            let from = rotated_path(&self.path, i);
+            if from.exists() {
+                std::fs::rename(&from, rotated_path(&self.path, i + 1))?;
+            }
+        }
+        std::fs::rename(&self.path, rotated_path(&self.path, 1))?;
+
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+
+        Ok(())
+    }
+}
+
+/// The last `n` lines of a launcher's log, oldest first. Reaches back into the
+/// previous log file if the current one was only just rotated.
+pub fn tail(name: &str, n: usize) -> std::io::Result<Vec<String>> {
+    let path = log_path(name);
+
+    let mut lines = read_lines(&path)?;
+    if lines.len() < n {
+        let mut older = read_lines(&rotated_path(&path, 1))?;
+        older.append(&mut lines);
+        lines = older;
+    }
+
+    let skip = lines.len().saturating_sub(n);
+    Ok(lines.split_off(skip))
+}
+
+fn read_lines(path: &Path) -> std::io::Result<Vec<String>> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    let bytes = std::fs::read(path)?;
+    Ok(String::from_utf8_lossy(&bytes).lines().map(|line| line.to_string()).collect())
+}
+
+fn log_path(name: &str) -> PathBuf {
+    use sha2::{Sha256, Digest};
+
+    // Launcher names are free text, keep them from escaping the logs directory
+    let file_name = name
+        .chars()
+        .map(|ch| if ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' { ch } else { '_' })
+        .collect::<String>();
+
+    // Sanitizing maps different names to the same file name ("Foo Bar" and
+    // "Foo_Bar"), so tell them apart by a hash of the real name
+    let hash = Sha256::digest(name.as_bytes())
+        .iter()
+        .take(4)
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+
+    PROJECT_DIRS.data_dir().join("logs").join(format!("{}-{}.log", file_name, hash))
+}
+
+fn rotated_path(path: &Path, i: usize) -> PathBuf {
+    let mut path = path.to_path_buf().into_os_string();
+    path.push(format!(".{}", i));
+    path.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_names_are_sanitized() {
+        let path = log_path("../../etc/passwd");
+
+        assert_eq!(path.parent(), Some(PROJECT_DIRS.data_dir().join("logs").as_path()));
+        let file_name = path.file_name().unwrap().to_str().unwrap();
+        assert!(file_name.starts_with("______etc_passwd-"));
+        assert!(file_name.ends_with(".log"));
+    }
+
+    #[test]
+    fn log_names_tell_sanitized_names_apart() {
+        assert_ne!(log_path("Foo Bar"), log_path("Foo_Bar"));
+        assert_eq!(log_path("Foo Bar"), log_path("Foo Bar"));
+    }
+
+    #[test]
+    fn rotated_paths_are_numbered() {
+        assert_eq!(rotated_path(Path::new("/logs/a.log"), 2), PathBuf::from("/logs/a.log.2"));
+    }
+
+    #[test]
+    fn rotating_drops_the_oldest_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.log");
+        let file = OpenOptions::new().create(true).append(true).open(&path).unwrap();
+        let mut inner = Inner { path: path.clone(), file, size: 0 };
+
+        for i in 0..=MAX_ROTATIONS {
+            inner.file.write_all(format!("{}\n", i).as_bytes()).unwrap();
+            inner.rotate().unwrap();
+        }
+
+        assert_eq!(read_lines(&path).unwrap(), Vec::<String>::new());
+        for i in 1..=MAX_ROTATIONS {
+            let expected = (MAX_ROTATIONS + 1 - i).to_string();
+            assert_eq!(read_lines(&rotated_path(&path, i)).unwrap(), vec![expected]);
+        }
+        assert!(!rotated_path(&path, MAX_ROTATIONS + 1).exists());
+    }
+}
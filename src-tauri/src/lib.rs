@@ -1,3 +1,4 @@
 pub mod api;
 pub mod config;
+pub mod secrets;
 pub mod util;
\ No newline at end of file
@@ -4,13 +4,20 @@
 use std::sync::{Arc, Mutex, atomic::AtomicBool};
 use tauri::{AppHandle, Wry, Manager};
 
+mod app_listener;
 mod audio_visualizer;
+mod config_bundle;
 mod config_listener;
+mod desktop_entries;
+mod icons;
 mod launcher;
+mod launcher_log;
 mod suggest_launchers;
 mod media_player;
+mod palette;
 mod remote_server;
 mod weather;
+mod window_manager;
 
 /// Global handle for the Tauri app. Since Tauri only gives you access to events
 /// if you have a handle to an app, and it only exposes this in a callback from
@@ -54,15 +61,20 @@ async fn main() -> anyhow::Result<()> {
 
     let config_manager = ConfigManager::new(global_app_handle.clone());
 
-    let launcher_manager = LauncherManager::new();
+    let launcher_manager = LauncherManager::new(global_app_handle.clone(), &config_manager);
 
     let audio_visualizer_manager = AudioVisualizerManager::new(&config_manager).unwrap();
+
+    let port_app = config_manager.config.read().unwrap().remote_server.port_app();
     
     let mut builder = tauri::Builder::default()
         .setup(move |app| {
             let mut handle = global_app_handle.handle.lock().unwrap();
             *handle = Some(app.handle());
             global_app_handle.exists.store(true, std::sync::atomic::Ordering::Relaxed);
+
+            tauri::async_runtime::spawn(app_listener::run(app.handle(), port_app));
+
             Ok(())
         })
         .manage(config_manager)
@@ -80,7 +92,20 @@ async fn main() -> anyhow::Result<()> {
             // commands::keystone_correct,
             config_listener::get_config,
             config_listener::set_config,
+            config_listener::list_profiles,
+            config_listener::create_profile,
+            config_listener::clone_profile,
+            config_listener::switch_profile,
+            config_listener::delete_profile,
+            config_listener::set_profile_pin,
+            config_bundle::export_config,
+            config_bundle::import_config,
             launcher::launch,
+            launcher::list_running,
+            launcher::stop_launcher,
+            launcher::restart_launcher,
+            launcher::get_launcher_log,
+            icons::fill_launcher_icons,
             suggest_launchers::suggest_launchers,
             weather::get_weather,
             audio_visualizer::get_audio_spectrum,
@@ -0,0 +1,195 @@
+//! Launcher backgrounds derived from launcher images.
+//!
+//! The image is shrunk down, its opaque pixels are sorted into coarse color
+//! buckets, and the most common colors become a gradient in the same shape as
+//! the hand-picked ones in `suggest_launchers`: bright on the left, fading to
+//! near-black on the right.
+
+use std::path::Path;
+
+/// Images are sampled at this size, which is plenty to find a few colors
+const SAMPLE_SIZE: u32 = 32;
+/// Bits kept per channel when bucketing colors
+const BUCKET_BITS: u32 = 4;
+
+type Rgb = [u8; 3];
+
+/// A `css_background` for a launcher with the image at `path`. Handles PNG,
+/// JPEG and SVG images.
+pub fn gradient(path: &Path) -> Option<String> {
+    let pixels = match sample(path) {
+        Ok(pixels) => pixels,
+        Err(err) => {
+            log::error!("Could not read colors from {}: {}", path.display(), err);
+            return None;
+        }
+    };
+
+    let palette = palette(&pixels);
+    let main = *palette.first()?;
+    let accent = palette.get(1).copied().unwrap_or(main);
+
+    Some(format!(
+        "linear-gradient(105deg, {} 0%, {} 50%, {} 100%)",
+        hex(main),
+        hex(scale(accent, 0.35)),
+        hex(scale(main, 0.08))
+    ))
+}
+
+/// RGBA pixels of the image at `SAMPLE_SIZE`.
+fn sample(path: &Path) -> anyhow::Result<Vec<[u8; 4]>> {
+    let is_svg = path
+        .extension()
+        .is_some_and(|extension| extension == "svg" || extension == "svgz");
+
+    if is_svg {
+        sample_svg(path)
+    }
+    else {
+        let image = image::open(path)?
+            .thumbnail(SAMPLE_SIZE, SAMPLE_SIZE)
+            .to_rgba8();
+
+        Ok(image.pixels().map(|pixel| pixel.0).collect())
+    }
+}
+
+fn sample_svg(path: &Path) -> anyhow::Result<Vec<[u8; 4]>> {
+    use resvg::{tiny_skia, usvg::{self, TreeParsing}};
+
+    let data = std::fs::read(path)?;
+    let tree = usvg::Tree::from_data(&data, &usvg::Options::default())?;
+    let tree = resvg::Tree::from_usvg(&tree);
+
+    let mut pixmap = tiny_skia::Pixmap::new(SAMPLE_SIZE, SAMPLE_SIZE)
+        .ok_or(anyhow::anyhow!("Could not allocate pixmap"))?;
+    let transform = tiny_skia::Transform::from_scale(
+        SAMPLE_SIZE as f32 / tree.size.width(),
+        SAMPLE_SIZE as f32 / tree.size.height()
+    );
+    tree.render(transform, &mut pixmap.as_mut());
+
+    // Pixmaps are premultiplied, but only opaque-ish pixels get used, where
+    // that makes little difference
+    Ok(pixmap
+        .pixels()
+        .iter()
+        .map(|pixel| [pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()])
+        .collect())
+}
+
+/// The image's colors, most common first. Greys count for less, otherwise
+/// every icon with an outline or a white background would come out grey.
+fn palette(pixels: &[[u8; 4]]) -> Vec<Rgb> {
+    use std::collections::BTreeMap;
+
+    // Bucket -> (weight, channel sums, pixel count)
+    let mut buckets: BTreeMap<Rgb, (f64, [u64; 3], u64)> = BTreeMap::new();
+    for [r, g, b, a] in pixels.iter().copied() {
+        if a < 128 {
+            continue;
+        }
+
+        let shift = 8 - BUCKET_BITS;
+        let bucket = buckets.entry([r >> shift, g >> shift, b >> shift]).or_default();
+        bucket.0 += 0.2 + saturation([r, g, b]);
+        bucket.1[0] += r as u64;
+        bucket.1[1] += g as u64;
+        bucket.1[2] += b as u64;
+        bucket.2 += 1;
+    }
+
+    let mut buckets = buckets.into_values().collect::<Vec<_>>();
+    buckets.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut palette: Vec<Rgb> = vec![];
+    for (_, sums, count) in buckets {
+        let color = sums.map(|sum| (sum / count) as u8);
+
+        // Neighbouring buckets are usually the same color, skip those
+        if palette.iter().all(|other| distance(*other, color) > 48.0) {
+            palette.push(color);
+        }
+        if palette.len() == 3 {
+            break;
+        }
+    }
+
+    palette
+}
+
+fn saturation([r, g, b]: Rgb) -> f64 {
+    let max = r.max(g).max(b) as f64;
+    let min = r.min(g).min(b) as f64;
+
+    if max == 0.0 { 0.0 } else { (max - min) / max }
+}
+
+fn distance(a: Rgb, b: Rgb) -> f64 {
+    (0..3)
+        .map(|i| (a[i] as f64 - b[i] as f64).powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+fn scale(color: Rgb, factor: f64) -> Rgb {
+    color.map(|channel| (channel as f64 * factor).round() as u8)
+}
+
+fn hex([r, g, b]: Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 4] = [220, 20, 20, 255];
+    const BLUE: [u8; 4] = [20, 20, 220, 255];
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+    fn pixels(counts: &[([u8; 4], usize)]) -> Vec<[u8; 4]> {
+        counts
+            .iter()
+            .flat_map(|(pixel, count)| std::iter::repeat(*pixel).take(*count))
+            .collect()
+    }
+
+    #[test]
+    fn most_common_colors_come_first() {
+        assert_eq!(palette(&pixels(&[(BLUE, 10), (RED, 30)])), vec![[220, 20, 20], [20, 20, 220]]);
+    }
+
+    #[test]
+    fn greys_count_for_less() {
+        // A white background twice the size of the icon's color still loses
+        assert_eq!(palette(&pixels(&[(WHITE, 20), (RED, 10)]))[0], [220, 20, 20]);
+    }
+
+    #[test]
+    fn transparent_pixels_are_ignored() {
+        assert_eq!(palette(&pixels(&[([0, 0, 0, 0], 100), (RED, 1)])), vec![[220, 20, 20]]);
+        assert!(palette(&pixels(&[([0, 0, 0, 0], 100)])).is_empty());
+    }
+
+    #[test]
+    fn similar_colors_are_merged() {
+        let palette = palette(&pixels(&[(RED, 10), ([200, 20, 20, 255], 10)]));
+
+        assert_eq!(palette.len(), 1);
+    }
+
+    #[test]
+    fn gradients_are_read_from_images() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("icon.png");
+        image::RgbaImage::from_pixel(8, 8, image::Rgba(RED)).save(&path).unwrap();
+
+        assert_eq!(
+            gradient(&path).as_deref(),
+            Some("linear-gradient(105deg, #dc1414 0%, #4d0707 50%, #120202 100%)")
+        );
+        assert_eq!(gradient(&dir.path().join("missing.png")), None);
+    }
+}
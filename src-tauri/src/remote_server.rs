@@ -67,6 +67,23 @@ pub async fn update_pending(config: State<'_, ConfigManager>, uuid: Uuid, approv
         })
 }
 
+/// Tell the remote server which launcher is in the foreground. The remote
+/// server might not be running, so failures are only logged.
+pub async fn set_foreground(port_internal: u16, launcher: Option<String>) {
+    let url = format!("http://localhost:{}/api/foreground", port_internal);
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(url)
+        .json(&Foreground { launcher })
+        .send()
+        .await;
+
+    if let Err(err) = res {
+        log::debug!("Could not send the foreground launcher to the remote server: {}", err);
+    }
+}
+
 #[derive(Serialize)]
 pub struct RemoteServerIp {
     name: String,
@@ -0,0 +1,335 @@
+//! Storage for secrets (API keys, signing keys) that shouldn't live in
+//! `config.json`.
+//!
+//! Secrets are kept in the freedesktop Secret Service when one is running, and
+//! otherwise in `secrets.json` next to the config files, readable only by the
+//! current user. Config files refer to secrets by name (see `config::Secret`).
+//!
+//! The app and the remote server both open the store, so whichever opens it
+//! first picks the backend and records the choice for the other. Both go
+//! through a lock file for that, and for changes to `secrets.json`.
+
+use crate::{
+    config::{Config, Secret},
+    util::project_dirs
+};
+
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::Mutex
+};
+
+const SECRETS_FILE: &str = "secrets.json";
+const BACKEND_FILE: &str = "secrets-backend";
+
+const FILE_BACKEND: &str = "file";
+#[cfg(target_os = "linux")]
+const SERVICE_BACKEND: &str = "secret-service";
+
+pub const WEATHER_API_KEY: &str = "weather.api_key";
+pub const JWT_SECRET: &str = "remote_server.jwt_secret";
+
+pub struct SecretStore {
+    backend: Backend,
+    // Reading from the Secret Service is a D-Bus round trip, and some
+    // implementations prompt on every access, so values are cached
+    cache: Mutex<BTreeMap<String, String>>
+}
+
+enum Backend {
+    File(PathBuf),
+    #[cfg(target_os = "linux")]
+    SecretService
+}
+
+impl SecretStore {
+    /// Open the store with the backend recorded in the config directory, or
+    /// pick the best available one and record it.
+    pub fn open() -> Self {
+        let dir = project_dirs::config_dir();
+        let file_path = dir.join(SECRETS_FILE);
+        let choice_path = dir.join(BACKEND_FILE);
+
+        // Held while choosing, so the app and the remote server can't both
+        // choose at once
+        let _lock = file::lock(&file_path, true)
+            .map_err(|err| log::error!("Could not lock the secrets file: {}", err))
+            .ok();
+
+        let recorded = std::fs::read_to_string(&choice_path).unwrap_or_default();
+        let backend = match recorded.trim() {
+            FILE_BACKEND => Backend::File(file_path),
+            #[cfg(target_os = "linux")]
+            SERVICE_BACKEND => Backend::SecretService,
+            _ => {
+                let (backend, name) = Self::choose(file_path);
+                if let Err(err) = std::fs::write(&choice_path, name) {
+                    log::error!("Could not record the secrets backend: {}", err);
+                }
+
+                backend
+            }
+        };
+
+        match &backend {
+            Backend::File(path) => log::info!("Using {} for secrets", path.display()),
+            #[cfg(target_os = "linux")]
+            Backend::SecretService => log::info!("Using Secret Service for secrets")
+        }
+
+        SecretStore::with_backend(backend)
+    }
+
+    /// The best available backend, and its name for `BACKEND_FILE`.
+    fn choose(file_path: PathBuf) -> (Backend, &'static str) {
+        #[cfg(target_os = "linux")]
+        {
+            match service::ensure_available() {
+                Ok(()) => return (Backend::SecretService, SERVICE_BACKEND),
+                Err(err) => {
+                    log::info!("Secret Service unavailable ({}), using secrets file", err);
+                }
+            }
+        }
+
+        (Backend::File(file_path), FILE_BACKEND)
+    }
+
+    pub fn open_file<P: Into<PathBuf>>(path: P) -> Self {
+        SecretStore::with_backend(Backend::File(path.into()))
+    }
+
+    fn with_backend(backend: Backend) -> Self {
+        SecretStore {
+            backend,
+            cache: Mutex::new(BTreeMap::new())
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.load(name)
+            .map_err(|err| {
+                log::error!("Error while reading secret \"{}\": {}", name, err);
+            })
+            .ok()
+            .flatten()
+    }
+
+    /// Like `get`, but tells a missing secret apart from one that couldn't be
+    /// read.
+    fn load(&self, name: &str) -> anyhow::Result<Option<String>> {
+        if let Some(value) = self.cache.lock().unwrap().get(name) {
+            return Ok(Some(value.clone()));
+        }
+
+        let value = match &self.backend {
+            Backend::File(path) => {
+                let _lock = file::lock(path, false)?;
+                file::read(path)?.remove(name)
+            }
+            #[cfg(target_os = "linux")]
+            Backend::SecretService => service::get(name)?
+        };
+
+        if let Some(value) = &value {
+            self.cache.lock().unwrap().insert(name.to_string(), value.clone());
+        }
+
+        Ok(value)
+    }
+
+    pub fn set(&self, name: &str, value: &str) -> anyhow::Result<()> {
+        match &self.backend {
+            Backend::File(path) => {
+                let _lock = file::lock(path, true)?;
+                let mut secrets = file::read(path)?;
+                secrets.insert(name.to_string(), value.to_string());
+                file::write(path, &secrets)?;
+            }
+            #[cfg(target_os = "linux")]
+            Backend::SecretService => service::set(name, value)?
+        }
+
+        self.cache.lock().unwrap().insert(name.to_string(), value.to_string());
+
+        Ok(())
+    }
+
+    /// Get a secret, generating and storing it first if it doesn't exist yet.
+    /// A secret that exists but can't be read is an error, rather than getting
+    /// replaced.
+    pub fn get_or_generate<F: FnOnce() -> String>(&self, name: &str, generate: F) -> anyhow::Result<String> {
+        if let Some(value) = self.load(name)? {
+            return Ok(value);
+        }
+
+        log::info!("Generating secret \"{}\"", name);
+        let value = generate();
+        self.set(name, &value)?;
+
+        Ok(value)
+    }
+}
+
+impl Secret {
+    pub fn resolve(&self, store: &SecretStore) -> Option<String> {
+        match self {
+            Secret::Stored { secret } => store.get(secret),
+            Secret::Inline(value) => Some(value.clone())
+        }
+    }
+
+    /// Move an inline value into the store under `name`. Returns whether
+    /// anything changed.
+    fn stash(&mut self, name: &str, store: &SecretStore) -> bool {
+        if let Secret::Inline(value) = self {
+            if let Err(err) = store.set(name, value) {
+                log::error!("Could not store secret \"{}\": {}", name, err);
+                return false;
+            }
+
+            *self = Secret::Stored { secret: name.to_string() };
+            return true;
+        }
+
+        false
+    }
+}
+
+impl Config {
+    /// Move any inline secrets into the secret store, returning whether the
+    /// config changed (and should be saved).
+    pub fn stash_secrets(&mut self, store: &SecretStore) -> bool {
+        let mut changed = false;
+
+        if let Some(api_key) = self.weather.as_mut().and_then(|weather| weather.api_key.as_mut()) {
+            changed |= api_key.stash(WEATHER_API_KEY, store);
+        }
+
+        changed
+    }
+}
+
+/// Plain file backend. The file is a flat JSON object of names to values, and
+/// is only ever created with owner-only permissions.
+mod file {
+    use std::{
+        collections::BTreeMap,
+        fs::File,
+        path::Path
+    };
+
+    /// An advisory lock (`flock`) on a lock file next to `path`, held until
+    /// dropped. Does nothing on platforms without `flock`.
+    pub struct Lock {
+        _file: File
+    }
+
+    pub fn lock(path: &Path, exclusive: bool) -> anyhow::Result<Lock> {
+        use std::fs::OpenOptions;
+
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path.with_extension("lock"))?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::io::AsRawFd;
+
+            let operation = if exclusive { libc::LOCK_EX } else { libc::LOCK_SH };
+            if unsafe { libc::flock(file.as_raw_fd(), operation) } != 0 {
+                return Err(std::io::Error::last_os_error().into());
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = exclusive;
+
+        Ok(Lock { _file: file })
+    }
+
+    pub fn read(path: &Path) -> anyhow::Result<BTreeMap<String, String>> {
+        if !path.exists() {
+            return Ok(BTreeMap::new());
+        }
+
+        let bytes = std::fs::read(path)?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    pub fn write(path: &Path, secrets: &BTreeMap<String, String>) -> anyhow::Result<()> {
+        use std::{fs::OpenOptions, io::Write};
+
+        let mut oo = OpenOptions::new();
+        oo.write(true).truncate(true).create(true);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            oo.mode(0o600);
+        }
+
+        let mut file = oo.open(path)?;
+
+        // `mode` only applies to newly created files, so tighten up any file
+        // that was created some other way
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+        }
+
+        file.write_all(&serde_json::to_vec_pretty(secrets)?)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod service {
+    use secret_service::{EncryptionType, blocking::SecretService};
+    use std::collections::HashMap;
+
+    const APPLICATION: &str = "macropinna";
+
+    fn attributes(name: &str) -> HashMap<&str, &str> {
+        HashMap::from([("application", APPLICATION), ("name", name)])
+    }
+
+    pub fn ensure_available() -> anyhow::Result<()> {
+        let ss = SecretService::connect(EncryptionType::Dh)?;
+        ss.get_default_collection()?;
+
+        Ok(())
+    }
+
+    pub fn get(name: &str) -> anyhow::Result<Option<String>> {
+        let ss = SecretService::connect(EncryptionType::Dh)?;
+        let collection = ss.get_default_collection()?;
+        collection.ensure_unlocked()?;
+
+        let items = collection.search_items(attributes(name))?;
+        match items.first() {
+            Some(item) => Ok(Some(String::from_utf8(item.get_secret()?)?)),
+            None => Ok(None)
+        }
+    }
+
+    pub fn set(name: &str, value: &str) -> anyhow::Result<()> {
+        let ss = SecretService::connect(EncryptionType::Dh)?;
+        let collection = ss.get_default_collection()?;
+        collection.ensure_unlocked()?;
+
+        collection.create_item(
+            &format!("Macropinna: {}", name),
+            attributes(name),
+            value.as_bytes(),
+            true,
+            "text/plain"
+        )?;
+
+        Ok(())
+    }
+}
@@ -1,104 +1,151 @@
-use shared::config::*;
+use crate::{desktop_entries, icons};
+use shared::{config::*, util::project_dirs};
+
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// User additions to the bundled catalog, in the config dir.
+const USER_CATALOG_FILE: &str = "launcher_catalog.json";
+
+/// An app we know how to launch nicely, suggested if it's installed.
+#[derive(Deserialize)]
+struct CatalogEntry {
+    check: Check,
+    /// Gradient stops for the launcher's background
+    colors: Option<[String; 3]>,
+    #[serde(flatten)]
+    launcher: Launcher
+}
+
+/// How to tell whether an app is installed.
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Check {
+    /// A program on `$PATH`
+    Binary(String),
+    /// A Flatpak app ID, installed either system-wide or per-user
+    Flatpak(String),
+    /// Any file or directory (`~/` is expanded)
+    FileExists(String)
+}
+
+impl Check {
+    fn passes(&self) -> bool {
+        match self {
+            Check::Binary(program) => desktop_entries::find_executable(program).is_some(),
+            Check::Flatpak(id) => {
+                let mut dirs = vec![PathBuf::from("/var/lib/flatpak")];
+                if let Some(base_dirs) = directories::BaseDirs::new() {
+                    dirs.push(base_dirs.data_dir().join("flatpak"));
+                }
+
+                dirs.iter().any(|dir| dir.join("app").join(id).exists())
+            }
+            Check::FileExists(path) => expand_home(path).exists()
+        }
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), directories::BaseDirs::new()) {
+        (Some(rest), Some(base_dirs)) => base_dirs.home_dir().join(rest),
+        _ => PathBuf::from(path)
+    }
+}
+
+/// The bundled catalog, with the user's catalog on top. A user entry replaces
+/// every bundled entry of the same name.
+fn load_catalog() -> Vec<CatalogEntry> {
+    let bundled: Vec<CatalogEntry> = serde_json::from_str(include_str!("launcher_catalog.json")).unwrap();
+
+    let user = read_catalog(&project_dirs::config_dir().join(USER_CATALOG_FILE));
+
+    let mut catalog = bundled
+        .into_iter()
+        .filter(|entry| !user.iter().any(|user_entry| user_entry.launcher.name == entry.launcher.name))
+        .collect::<Vec<_>>();
+    catalog.extend(user);
+
+    catalog
+}
+
+fn read_catalog(path: &Path) -> Vec<CatalogEntry> {
+    if !path.exists() {
+        return vec![];
+    }
+
+    let catalog = std::fs::read(path)
+        .map_err(anyhow::Error::from)
+        .and_then(|bytes| Ok(serde_json::from_slice(&bytes)?));
+
+    match catalog {
+        Ok(catalog) => catalog,
+        Err(err) => {
+            log::error!("Could not read launcher catalog {}: {}", path.display(), err);
+            vec![]
+        }
+    }
+}
 
 #[tauri::command]
-pub fn suggest_launchers() -> Vec<Launcher> {
-    use std::process::Command;
-
-    struct FindLauncher {
-        checker: &'static str,
-        name: &'static str,
-        command: &'static str,
-        finder: &'static str,
-        finder_is_regex: bool,
-        colors: [&'static str; 3]
+pub async fn suggest_launchers() -> Result<Vec<Launcher>, String> {
+    // Scanning desktop entries and decoding every icon for its gradient takes
+    // a while, keep it off the main thread
+    tokio::task::spawn_blocking(suggest)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+fn suggest() -> Vec<Launcher> {
+    let entries = desktop_entries::discover();
+
+    // Entries of the same name are alternatives (e.g. a native package and a
+    // Flatpak), only the first one installed gets suggested
+    let mut installed: Vec<CatalogEntry> = vec![];
+    for entry in load_catalog() {
+        let is_duplicate = installed
+            .iter()
+            .any(|other| other.launcher.name == entry.launcher.name);
+
+        if !is_duplicate && entry.check.passes() {
+            installed.push(entry);
+        }
     }
 
-    // TODO find a way to move this to a separate (data) file - may involve an
-    // extra build step
-    let find_launchers = [
-        // Media
-        FindLauncher {
-            checker: "which spotify",
-            name: "Spotify",
-            command: "spotify",
-            finder: "Spotify",
-            finder_is_regex: false,
-            colors: ["#23cf5f", "#0c4620", "#030e06"]
-        },
-        FindLauncher {
-            checker: "which kodi",
-            name: "Kodi",
-            command: "kodi",
-            finder: "Kodi",
-            finder_is_regex: false,
-            colors: ["#12b2e7", "#063646", "#000000"]
-        },
-        // Browsing
-        FindLauncher {
-            checker: "which firefox",
-            name: "Firefox",
-            command: "firefox",
-            finder: "Mozilla Firefox",
-            finder_is_regex: false,
-            colors: ["#ffa048", "#e06c4d", "#a40757"]
-        },
-        FindLauncher {
-            checker: "which firefox",
-            name: "Reddit",
-            command: "firefox --new-window \"https://www.reddit.com/\"",
-            finder: "Reddit",
-            finder_is_regex: false,
-            colors: ["#ffffff", "#ffbfa8", "#ff4300"]
-        },
-        FindLauncher {
-            checker: "which firefox",
-            name: "YouTube",
-            command: "firefox --new-window \"https://www.youtube.com/\"",
-            finder: "YouTube",
-            finder_is_regex: false,
-            colors: ["#ffffff", "#ffadad", "#ff0000"]
-        },
-        // Admin/other
-        FindLauncher {
-            checker: "which gnome-terminal",
-            name: "Terminal",
-            command: "gnome-terminal",
-            finder: "Terminal",
-            finder_is_regex: false,
-            colors: ["#dfdedb", "#4d4857", "#221c30"]
-        },
-        FindLauncher {
-            checker: "which xfce4-terminal",
-            name: "Terminal",
-            command: "xfce4-terminal",
-            finder: "Terminal",
-            finder_is_regex: false,
-            colors: ["#dfdedb", "#4d4857", "#221c30"]
-        },
-    ];
-
-    find_launchers
+    let mut launchers = installed
         .into_iter()
-        .filter(|find_launcher| {
-            Command::new("sh")
-                .args(["-c", find_launcher.checker])
-                .output()
-                .is_ok_and(|output| output.status.success())
-        })
-        .map(|find_launcher| {
-            Launcher {
-                name: find_launcher.name.to_string(),
-                command: find_launcher.command.to_string(),
-                finder: find_launcher.finder.to_string(),
-                finder_is_regex: if find_launcher.finder_is_regex { Some(true) } else { None },
-                image_path: None,
-                css_background: Some(format!(
+        .map(|entry| {
+            let mut launcher = entry.launcher;
+
+            if let (None, Some(colors)) = (&launcher.css_background, &entry.colors) {
+                launcher.css_background = Some(format!(
                     "linear-gradient(105deg, {} 0%, {} 50%, {} 100%)",
-                    find_launcher.colors[0],
-                    find_launcher.colors[1],
-                    find_launcher.colors[2],
-                ))
+                    colors[0],
+                    colors[1],
+                    colors[2],
+                ));
+            }
+
+            if launcher.image_path.is_none() {
+                launcher.image_path = icons::guess(&launcher, &entries)
+                    .map(|path| path.to_string_lossy().to_string());
             }
+
+            launcher
         })
-        .collect()
-}
\ No newline at end of file
+        .collect::<Vec<_>>();
+
+    // Anything else installed, unless the catalog already covers it. Checked
+    // before converting, which works out the entry's gradient
+    for entry in entries {
+        let is_known = launchers
+            .iter()
+            .any(|known| known.name.eq_ignore_ascii_case(&entry.name) || known.command == entry.exec[0]);
+
+        if !is_known {
+            launchers.push(entry.to_launcher());
+        }
+    }
+
+    launchers
+}
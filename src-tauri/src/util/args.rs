@@ -0,0 +1,59 @@
+//! Command line and environment options shared by the app and the remote
+//! server binary.
+//!
+//! Both understand:
+//! - `--config <dir>` (or `MACROPINNA_CONFIG_DIR`): use another directory for
+//!   config files instead of the platform default
+//! - `--read-only` (or `MACROPINNA_READ_ONLY=1`): never write config files
+//!
+//! Command line flags take precedence over environment variables.
+
+use lazy_static::lazy_static;
+use std::path::PathBuf;
+
+const CONFIG_DIR_VAR: &str = "MACROPINNA_CONFIG_DIR";
+const READ_ONLY_VAR: &str = "MACROPINNA_READ_ONLY";
+
+lazy_static! {
+    pub static ref ARGS: Args = Args::from_env();
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Args {
+    pub config_dir: Option<PathBuf>,
+    pub read_only: bool
+}
+
+impl Args {
+    fn from_env() -> Self {
+        let mut args = Args {
+            config_dir: std::env::var_os(CONFIG_DIR_VAR)
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from),
+            read_only: std::env::var(READ_ONLY_VAR)
+                .is_ok_and(|val| matches!(val.as_str(), "1" | "true" | "yes"))
+        };
+
+        args.parse(std::env::args().skip(1));
+        args
+    }
+
+    /// Pick out the flags we care about, ignoring anything else (Tauri and
+    /// cargo both like to pass their own arguments through).
+    fn parse<I: Iterator<Item = String>>(&mut self, mut iter: I) {
+        while let Some(arg) = iter.next() {
+            if arg == "--config" {
+                match iter.next() {
+                    Some(dir) => self.config_dir = Some(dir.into()),
+                    None => log::error!("--config needs a directory")
+                }
+            }
+            else if let Some(dir) = arg.strip_prefix("--config=") {
+                self.config_dir = Some(dir.into());
+            }
+            else if arg == "--read-only" {
+                self.read_only = true;
+            }
+        }
+    }
+}
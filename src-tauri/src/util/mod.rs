@@ -1,2 +1,3 @@
+pub mod args;
 pub mod numeric;
 pub mod project_dirs;
\ No newline at end of file
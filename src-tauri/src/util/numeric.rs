@@ -6,4 +6,13 @@ pub fn try_f64_to_i32(x: f64) -> Option<i32> {
     if x < i32::MIN as f64 { return None; }
 
     return Some(x as i32);
+}
+
+/// Move `average` a `weight` of the way toward `sample`, starting at `sample`
+/// if there is no average yet.
+pub fn moving_average(average: Option<f64>, sample: f64, weight: f64) -> f64 {
+    match average {
+        Some(average) => average + (sample - average) * weight,
+        None => sample
+    }
 }
\ No newline at end of file
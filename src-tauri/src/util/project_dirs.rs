@@ -1,5 +1,8 @@
 use directories::ProjectDirs;
 use lazy_static::lazy_static;
+use std::path::Path;
+
+use super::args::ARGS;
 
 lazy_static! {
     pub static ref PROJECT_DIRS: ProjectDirs = {
@@ -11,11 +14,23 @@ lazy_static! {
     };
 }
 
+/// Directory holding config files, respecting `--config`/`MACROPINNA_CONFIG_DIR`.
+pub fn config_dir() -> &'static Path {
+    ARGS.config_dir
+        .as_deref()
+        .unwrap_or_else(|| PROJECT_DIRS.config_dir())
+}
+
+/// Whether config files should be left untouched (`--read-only`).
+pub fn is_read_only() -> bool {
+    ARGS.read_only
+}
+
 /// Ensure project directories are initialized.
 pub fn ensure() {
     use std::fs;
 
-    if let Err(err) = fs::create_dir_all(PROJECT_DIRS.config_dir()) {
+    if let Err(err) = fs::create_dir_all(config_dir()) {
         log::error!("{}", err);
         panic!();
     }
@@ -24,4 +39,4 @@ pub fn ensure() {
         log::error!("{}", err);
         panic!();
     }
-}
\ No newline at end of file
+}
@@ -39,19 +39,24 @@ async fn _get_weather(config: State<'_, ConfigManager>) -> anyhow::Result<Weathe
         }
     };
 
+    let api_key = match weather.api_key.as_ref().and_then(|key| key.resolve(&config.secrets)) {
+        Some(api_key) => api_key,
+        None => anyhow::bail!("weather API key not configured")
+    };
+
     match weather.provider {
         WeatherProvider::OpenWeatherMap => {
             let current_url = format!(
                 "https://api.openweathermap.org/data/2.5/weather?lat={}&lon={}&appid={}",
                 weather.lat,
                 weather.long,
-                weather.api_key
+                api_key
             );
             let forecast_url = format!(
                 "https://api.openweathermap.org/data/2.5/forecast?lat={}&lon={}&appid={}",
                 weather.lat,
                 weather.long,
-                weather.api_key
+                api_key
             );
 
             let (current, forecast) = try_join!(
@@ -0,0 +1,146 @@
+//! KDE, through KWin scripting. KWin doesn't list windows over D-Bus, but it
+//! does run scripts sent to it over D-Bus, so the query is turned into a small
+//! script that raises (or minimizes, or checks) the matching windows and
+//! calls back with the result.
+
+use super::{Backend, TitleMatch, WindowAction, WindowQuery};
+
+use serde_json::json;
+use std::{
+    io::Write,
+    sync::{Mutex, mpsc},
+    time::Duration
+};
+use zbus::{blocking::Connection, dbus_interface};
+
+const KWIN: &str = "org.kde.KWin";
+const PLUGIN_NAME: &str = "macropinna-windows";
+const RESULT_PATH: &str = "/WindowQuery";
+const RESULT_INTERFACE: &str = "org.macropinna.WindowQuery";
+const RESULT_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub struct KWin;
+
+/// Receives the script's result.
+struct ResultReceiver {
+    sender: Mutex<mpsc::Sender<bool>>
+}
+
+#[dbus_interface(name = "org.macropinna.WindowQuery")]
+impl ResultReceiver {
+    fn result(&self, found: bool) {
+        let _ = self.sender.lock().unwrap().send(found);
+    }
+}
+
+impl Backend for KWin {
+    fn name(&self) -> &'static str {
+        "kwin"
+    }
+
+    fn apply(&self, query: &WindowQuery, action: WindowAction) -> anyhow::Result<bool> {
+        let conn = Connection::session()?;
+
+        let (sender, receiver) = mpsc::channel();
+        conn.object_server().at(RESULT_PATH, ResultReceiver { sender: Mutex::new(sender) })?;
+
+        let service = conn
+            .unique_name()
+            .ok_or(anyhow::anyhow!("No D-Bus name"))?
+            .to_string();
+
+        // Created exclusively under a random name, so nobody else can get a
+        // script (or a symlink) in its place. Removed when dropped
+        let mut file = tempfile::Builder::new()
+            .prefix(&format!("{}-", PLUGIN_NAME))
+            .suffix(".js")
+            .tempfile()?;
+        file.write_all(script(query, action, &service).as_bytes())?;
+        file.flush()?;
+
+        let result = run_script(&conn, &file.path().to_string_lossy())
+            .and_then(|()| Ok(receiver.recv_timeout(RESULT_TIMEOUT)?));
+
+        let _ = conn.call_method(Some(KWIN), "/Scripting", Some("org.kde.kwin.Scripting"), "unloadScript", &(PLUGIN_NAME,));
+
+        result
+    }
+}
+
+fn run_script(conn: &Connection, path: &str) -> anyhow::Result<()> {
+    // A script left over from an earlier run would keep this one from loading
+    conn.call_method(Some(KWIN), "/Scripting", Some("org.kde.kwin.Scripting"), "unloadScript", &(PLUGIN_NAME,))?;
+
+    let id: i32 = conn
+        .call_method(Some(KWIN), "/Scripting", Some("org.kde.kwin.Scripting"), "loadScript", &(path, PLUGIN_NAME))?
+        .body()?;
+
+    // Plasma 6 moved scripts to a different path
+    conn.call_method(Some(KWIN), format!("/Scripting/Script{}", id).as_str(), Some("org.kde.kwin.Script"), "run", &())
+        .or_else(|_| conn.call_method(Some(KWIN), format!("/{}", id).as_str(), Some("org.kde.kwin.Script"), "run", &()))?;
+
+    Ok(())
+}
+
+fn script(query: &WindowQuery, action: WindowAction, service: &str) -> String {
+    // Rust and JS regexes are close enough for window titles, except for
+    // inline flags
+    let title = match &query.title {
+        Some(TitleMatch::Exact(title)) => json!({ "exact": title }),
+        Some(TitleMatch::Regex(regex)) => match regex.as_str().strip_prefix("(?i)") {
+            Some(regex) => json!({ "regex": regex, "flags": "i" }),
+            None => json!({ "regex": regex.as_str(), "flags": "" })
+        },
+        None => json!(null)
+    };
+
+    format!(
+        r#"
+const title = {title};
+const appId = {app_id};
+const pids = {pids};
+const minimize = {minimize};
+const checkActive = {check_active};
+const windows = workspace.windowList ? workspace.windowList() : workspace.clientList();
+const active = "activeWindow" in workspace ? workspace.activeWindow : workspace.activeClient;
+
+function matches(window) {{
+    if (title && title.exact !== undefined && window.caption === title.exact) return true;
+    if (title && title.regex !== undefined && new RegExp(title.regex, title.flags).test(window.caption)) return true;
+    if (appId && [window.resourceClass, window.resourceName, window.desktopFileName]
+        .some((id) => id && String(id).toLowerCase() === appId.toLowerCase())) return true;
+    return pids.includes(window.pid);
+}}
+
+let found = false;
+for (const window of windows) {{
+    if (!matches(window)) continue;
+
+    if (checkActive) {{
+        if (window === active) found = true;
+        continue;
+    }}
+    found = true;
+
+    if (minimize) {{
+        window.minimized = true;
+    }}
+    else {{
+        if ("activeWindow" in workspace) workspace.activeWindow = window;
+        else workspace.activeClient = window;
+        break;
+    }}
+}}
+
+callDBus({service}, {path}, {interface}, "Result", found);
+"#,
+        title = title,
+        app_id = json!(query.app_id),
+        pids = json!(query.pids),
+        minimize = action == WindowAction::Minimize,
+        check_active = action == WindowAction::CheckActive,
+        service = json!(service),
+        path = json!(RESULT_PATH),
+        interface = json!(RESULT_INTERFACE)
+    )
+}
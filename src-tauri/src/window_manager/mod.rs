@@ -0,0 +1,198 @@
+//! Finding and raising the windows of running apps.
+//!
+//! There's no one way to do this across desktops, so there's a backend for
+//! each way we know: EWMH on X11, wlr-foreign-toplevel on wlroots compositors
+//! (Sway, Hyprland, ...), and D-Bus on KDE and GNOME. Backends that make sense
+//! for the current session are tried in order until one works.
+
+mod process;
+#[cfg(target_os = "linux")]
+mod gnome;
+#[cfg(target_os = "linux")]
+mod kwin;
+#[cfg(target_os = "linux")]
+mod wlr;
+#[cfg(unix)]
+mod x11;
+
+use regex::Regex;
+
+/// What a backend knows about a window.
+pub struct WindowInfo {
+    pub title: String,
+    /// `WM_CLASS` on X11, the app ID on Wayland
+    pub app_ids: Vec<String>,
+    pub pid: Option<u32>
+}
+
+/// Which windows to look for. A window matches if it matches any of these.
+#[derive(Default)]
+pub struct WindowQuery {
+    pub title: Option<TitleMatch>,
+    /// Compared case-insensitively, as apps aren't consistent about it
+    pub app_id: Option<String>,
+    /// Processes that may own the window
+    pub pids: Vec<u32>
+}
+
+pub enum TitleMatch {
+    Exact(String),
+    Regex(Regex)
+}
+
+impl WindowQuery {
+    /// A query for a launcher's `finder`. An invalid regex only gets logged,
+    /// leaving the title out of the query.
+    pub fn for_finder(finder: &str, is_regex: bool) -> Self {
+        let title =
+            if is_regex {
+                match Regex::new(finder) {
+                    Ok(regex) => Some(TitleMatch::Regex(regex)),
+                    Err(err) => {
+                        log::error!("Invalid finder regex \"{}\": {}", finder, err);
+                        None
+                    }
+                }
+            }
+            else {
+                Some(TitleMatch::Exact(finder.to_string()))
+            };
+
+        WindowQuery {
+            title,
+            ..Default::default()
+        }
+    }
+
+    /// Also match windows of the processes in `pgid`'s process group.
+    pub fn with_process_group(mut self, pgid: u32) -> Self {
+        self.pids.extend(process::process_group(pgid));
+        self
+    }
+
+    /// Also match windows of running processes with this name (the file name
+    /// of the executable) or executable path.
+    pub fn with_processes(mut self, name: Option<&str>, exe: Option<&str>) -> Self {
+        self.pids.extend(process::find(name, exe));
+        self
+    }
+
+    pub fn with_app_id(mut self, app_id: &str) -> Self {
+        self.app_id = Some(app_id.to_string());
+        self
+    }
+
+    /// Whether any of the processes in the query are running, for when the
+    /// windows themselves can't be checked.
+    pub fn has_processes(&self) -> bool {
+        !self.pids.is_empty()
+    }
+
+    pub fn matches(&self, window: &WindowInfo) -> bool {
+        let title_matches = match &self.title {
+            Some(TitleMatch::Exact(title)) => window.title == *title,
+            Some(TitleMatch::Regex(regex)) => regex.is_match(&window.title),
+            None => false
+        };
+
+        let app_id_matches = self.app_id.as_ref().is_some_and(|app_id| {
+            window.app_ids.iter().any(|window_app_id| window_app_id.eq_ignore_ascii_case(app_id))
+        });
+
+        title_matches || app_id_matches || window.pid.is_some_and(|pid| self.pids.contains(&pid))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum WindowAction {
+    /// Bring the first matching window to the front
+    Raise,
+    /// Minimize every matching window
+    Minimize,
+    /// Only check whether a matching window is the active one
+    CheckActive
+}
+
+trait Backend: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Apply `action` to windows matching `query`, returning whether there
+    /// were any (for `CheckActive`, whether one of them is active).
+    fn apply(&self, query: &WindowQuery, action: WindowAction) -> anyhow::Result<bool>;
+}
+
+pub struct WindowManager {
+    backends: Vec<Box<dyn Backend>>
+}
+
+impl WindowManager {
+    /// Pick backends for the current session.
+    pub fn detect() -> Self {
+        #[allow(unused_mut)]
+        let mut backends: Vec<Box<dyn Backend>> = vec![];
+
+        #[cfg(target_os = "linux")]
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            let desktop = std::env::var("XDG_CURRENT_DESKTOP")
+                .unwrap_or_default()
+                .to_lowercase();
+
+            if desktop.contains("kde") {
+                backends.push(Box::new(kwin::KWin));
+            }
+            if desktop.contains("gnome") {
+                backends.push(Box::new(gnome::Gnome));
+            }
+            backends.push(Box::new(wlr::Wlr));
+        }
+
+        // Also covers XWayland windows in a Wayland session
+        #[cfg(unix)]
+        if std::env::var_os("DISPLAY").is_some() {
+            backends.push(Box::new(x11::X11));
+        }
+
+        log::info!(
+            "Window manager backends: {:?}",
+            backends.iter().map(|backend| backend.name()).collect::<Vec<_>>()
+        );
+
+        WindowManager { backends }
+    }
+
+    /// Raise the first window matching `query`, returning whether there was
+    /// one. Fails if no backend could look for windows at all.
+    pub fn raise(&self, query: &WindowQuery) -> anyhow::Result<bool> {
+        self.apply(query, WindowAction::Raise)
+    }
+
+    /// Minimize the windows matching `query`, returning whether there were
+    /// any.
+    pub fn minimize(&self, query: &WindowQuery) -> anyhow::Result<bool> {
+        self.apply(query, WindowAction::Minimize)
+    }
+
+    /// Whether a window matching `query` is the active window.
+    pub fn is_active(&self, query: &WindowQuery) -> anyhow::Result<bool> {
+        self.apply(query, WindowAction::CheckActive)
+    }
+
+    fn apply(&self, query: &WindowQuery, action: WindowAction) -> anyhow::Result<bool> {
+        // Backends can see different windows (X11 only sees XWayland ones), so
+        // one not finding anything doesn't mean the next one won't
+        let mut worked = false;
+        for backend in &self.backends {
+            match backend.apply(query, action) {
+                Ok(true) => return Ok(true),
+                Ok(false) => worked = true,
+                Err(err) => log::error!("Window manager backend {} failed: {}", backend.name(), err)
+            }
+        }
+
+        if !worked {
+            anyhow::bail!("No working window manager backend");
+        }
+
+        Ok(false)
+    }
+}
@@ -0,0 +1,72 @@
+//! Looking up running processes, for matching windows by the process that
+//! owns them.
+
+/// Every process in a process group. Launchers are started in their own
+/// group, so this is the launcher's process along with anything it spawned
+/// (that didn't start its own group).
+pub fn process_group(pgid: u32) -> Vec<u32> {
+    let mut pids = vec![pgid];
+
+    pids.extend(
+        processes()
+            .into_iter()
+            .filter(|&pid| pid != pgid && stat_field(pid, 2) == Some(pgid))
+    );
+
+    pids
+}
+
+/// Running processes (of the current user) whose executable has the file
+/// name `name` or the path `exe`.
+pub fn find(name: Option<&str>, exe: Option<&str>) -> Vec<u32> {
+    if name.is_none() && exe.is_none() {
+        return vec![];
+    }
+
+    processes()
+        .into_iter()
+        .filter(|&pid| {
+            let path = match std::fs::read_link(format!("/proc/{}/exe", pid)) {
+                Ok(path) => path,
+                Err(_) => return false
+            };
+
+            let name_matches = name.is_some_and(|name| path.file_name().is_some_and(|file_name| file_name == name));
+            let exe_matches = exe.is_some_and(|exe| path == std::path::Path::new(exe));
+
+            name_matches || exe_matches
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn processes() -> Vec<u32> {
+    match std::fs::read_dir("/proc") {
+        Ok(read_dir) => read_dir
+            .flatten()
+            .filter_map(|dir_entry| dir_entry.file_name().to_string_lossy().parse::<u32>().ok())
+            .collect(),
+        Err(_) => vec![]
+    }
+}
+
+// TODO no /proc to go through elsewhere
+#[cfg(not(target_os = "linux"))]
+fn processes() -> Vec<u32> {
+    vec![]
+}
+
+/// A numeric field of `/proc/<pid>/stat`, counting from the one after the
+/// command name (0 = state, 1 = ppid, 2 = pgrp, ...).
+fn stat_field(pid: u32, n: usize) -> Option<u32> {
+    // The command name can contain spaces and parentheses, so fields are
+    // counted from the last `)`
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+
+    stat.rsplit_once(')')?
+        .1
+        .split_whitespace()
+        .nth(n)?
+        .parse()
+        .ok()
+}
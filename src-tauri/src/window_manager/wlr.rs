@@ -0,0 +1,174 @@
+//! wlroots-based compositors, through the wlr-foreign-toplevel-management
+//! protocol (the one taskbars use).
+
+use super::{Backend, WindowAction, WindowInfo, WindowQuery};
+
+use wayland_client::{
+    Connection, Dispatch, QueueHandle, event_created_child,
+    protocol::{wl_registry::{self, WlRegistry}, wl_seat::WlSeat}
+};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::{
+    zwlr_foreign_toplevel_handle_v1::{self, ZwlrForeignToplevelHandleV1},
+    zwlr_foreign_toplevel_manager_v1::{self, ZwlrForeignToplevelManagerV1}
+};
+
+pub struct Wlr;
+
+#[derive(Default)]
+struct State {
+    manager: Option<ZwlrForeignToplevelManagerV1>,
+    seat: Option<WlSeat>,
+    toplevels: Vec<Toplevel>
+}
+
+struct Toplevel {
+    handle: ZwlrForeignToplevelHandleV1,
+    title: String,
+    app_id: String,
+    activated: bool
+}
+
+impl Backend for Wlr {
+    fn name(&self) -> &'static str {
+        "wlr-foreign-toplevel"
+    }
+
+    fn apply(&self, query: &WindowQuery, action: WindowAction) -> anyhow::Result<bool> {
+        let conn = Connection::connect_to_env()?;
+        let mut queue = conn.new_event_queue();
+        let qh = queue.handle();
+
+        let mut state = State::default();
+        conn.display().get_registry(&qh, ());
+
+        // First roundtrip binds the globals, second one gets the toplevels
+        // along with their details
+        queue.roundtrip(&mut state)?;
+        let manager = state.manager
+            .clone()
+            .ok_or(anyhow::anyhow!("Compositor doesn't support wlr-foreign-toplevel-management"))?;
+        queue.roundtrip(&mut state)?;
+
+        let matching = state.toplevels
+            .iter()
+            .filter(|toplevel| query.matches(&toplevel.info()))
+            .collect::<Vec<_>>();
+
+        match (action, matching.first(), &state.seat) {
+            (WindowAction::Raise, Some(toplevel), Some(seat)) => toplevel.handle.activate(seat),
+            (WindowAction::Raise, Some(_), None) => anyhow::bail!("No seat to activate windows with"),
+            (WindowAction::Minimize, _, _) => {
+                for toplevel in &matching {
+                    toplevel.handle.set_minimized();
+                }
+            }
+            _ => {}
+        }
+        let found =
+            if action == WindowAction::CheckActive {
+                matching.iter().any(|toplevel| toplevel.activated)
+            }
+            else {
+                !matching.is_empty()
+            };
+
+        manager.stop();
+        queue.roundtrip(&mut state)?;
+
+        Ok(found)
+    }
+}
+
+impl Toplevel {
+    fn info(&self) -> WindowInfo {
+        // The protocol doesn't tell which process a toplevel belongs to
+        WindowInfo {
+            title: self.title.clone(),
+            app_ids: vec![self.app_id.clone()],
+            pid: None
+        }
+    }
+}
+
+impl Dispatch<WlRegistry, ()> for State {
+    fn event(
+        state: &mut Self,
+        registry: &WlRegistry,
+        event: wl_registry::Event,
+        _: &(),
+        _: &Connection,
+        qh: &QueueHandle<Self>
+    ) {
+        if let wl_registry::Event::Global { name, interface, version } = event {
+            match interface.as_str() {
+                "zwlr_foreign_toplevel_manager_v1" => {
+                    state.manager = Some(registry.bind(name, version.min(3), qh, ()));
+                }
+                "wl_seat" if state.seat.is_none() => {
+                    state.seat = Some(registry.bind(name, 1, qh, ()));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Dispatch<WlSeat, ()> for State {
+    fn event(_: &mut Self, _: &WlSeat, _: <WlSeat as wayland_client::Proxy>::Event, _: &(), _: &Connection, _: &QueueHandle<Self>) {}
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrForeignToplevelManagerV1,
+        event: zwlr_foreign_toplevel_manager_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>
+    ) {
+        if let zwlr_foreign_toplevel_manager_v1::Event::Toplevel { toplevel } = event {
+            state.toplevels.push(Toplevel {
+                handle: toplevel,
+                title: String::new(),
+                app_id: String::new(),
+                activated: false
+            });
+        }
+    }
+
+    event_created_child!(State, ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ())
+    ]);
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        handle: &ZwlrForeignToplevelHandleV1,
+        event: zwlr_foreign_toplevel_handle_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>
+    ) {
+        let toplevel = match state.toplevels.iter_mut().find(|toplevel| toplevel.handle == *handle) {
+            Some(toplevel) => toplevel,
+            None => return
+        };
+
+        match event {
+            zwlr_foreign_toplevel_handle_v1::Event::Title { title } => toplevel.title = title,
+            zwlr_foreign_toplevel_handle_v1::Event::AppId { app_id } => toplevel.app_id = app_id,
+            zwlr_foreign_toplevel_handle_v1::Event::State { state } => {
+                // An array of native-endian u32s
+                toplevel.activated = state
+                    .chunks_exact(4)
+                    .any(|chunk| u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]])
+                        == zwlr_foreign_toplevel_handle_v1::State::Activated as u32);
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::Closed => {
+                state.toplevels.retain(|toplevel| toplevel.handle != *handle);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! X11, through the EWMH properties the window manager keeps on the root
+//! window.
+
+use super::{Backend, WindowAction, WindowInfo, WindowQuery};
+
+use x11rb::{
+    connection::Connection,
+    protocol::xproto::{Atom, AtomEnum, ClientMessageEvent, ConnectionExt, EventMask, Window}
+};
+
+pub struct X11;
+
+struct Atoms {
+    net_client_list: Atom,
+    net_active_window: Atom,
+    wm_change_state: Atom,
+    net_wm_name: Atom,
+    net_wm_pid: Atom,
+    utf8_string: Atom
+}
+
+impl Backend for X11 {
+    fn name(&self) -> &'static str {
+        "x11"
+    }
+
+    fn apply(&self, query: &WindowQuery, action: WindowAction) -> anyhow::Result<bool> {
+        let (conn, screen_num) = x11rb::connect(None)?;
+        let root = conn.setup().roots[screen_num].root;
+
+        let atom = |name: &str| -> anyhow::Result<Atom> {
+            Ok(conn.intern_atom(false, name.as_bytes())?.reply()?.atom)
+        };
+        let atoms = Atoms {
+            net_client_list: atom("_NET_CLIENT_LIST")?,
+            net_active_window: atom("_NET_ACTIVE_WINDOW")?,
+            wm_change_state: atom("WM_CHANGE_STATE")?,
+            net_wm_name: atom("_NET_WM_NAME")?,
+            net_wm_pid: atom("_NET_WM_PID")?,
+            utf8_string: atom("UTF8_STRING")?
+        };
+
+        let windows = conn
+            .get_property(false, root, atoms.net_client_list, AtomEnum::WINDOW, 0, u32::MAX)?
+            .reply()?
+            .value32()
+            .map(|windows| windows.collect::<Vec<_>>())
+            .ok_or(anyhow::anyhow!("Window manager doesn't support _NET_CLIENT_LIST"))?;
+
+        if action == WindowAction::CheckActive {
+            let active = conn
+                .get_property(false, root, atoms.net_active_window, AtomEnum::WINDOW, 0, 1)?
+                .reply()?
+                .value32()
+                .and_then(|mut windows| windows.next())
+                .filter(|&window| window != x11rb::NONE);
+
+            return match active {
+                Some(window) => Ok(query.matches(&info(&conn, &atoms, window)?)),
+                None => Ok(false)
+            };
+        }
+
+        let mut found = false;
+        for window in windows {
+            if !query.matches(&info(&conn, &atoms, window)?) {
+                continue;
+            }
+
+            let event = match action {
+                // Source indication 2 = a pager, which window managers trust
+                // more than apps asking to be activated
+                WindowAction::Raise => ClientMessageEvent::new(
+                    32,
+                    window,
+                    atoms.net_active_window,
+                    [2, x11rb::CURRENT_TIME, 0, 0, 0]
+                ),
+                // 3 = IconicState
+                WindowAction::Minimize => ClientMessageEvent::new(
+                    32,
+                    window,
+                    atoms.wm_change_state,
+                    [3, 0, 0, 0, 0]
+                ),
+                WindowAction::CheckActive => unreachable!()
+            };
+            conn.send_event(
+                false,
+                root,
+                EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+                event
+            )?;
+            found = true;
+
+            if action == WindowAction::Raise {
+                break;
+            }
+        }
+        conn.flush()?;
+
+        Ok(found)
+    }
+}
+
+fn info<C: Connection>(conn: &C, atoms: &Atoms, window: Window) -> anyhow::Result<WindowInfo> {
+    let mut title = conn
+        .get_property(false, window, atoms.net_wm_name, atoms.utf8_string, 0, u32::MAX)?
+        .reply()?
+        .value;
+    if title.is_empty() {
+        title = conn
+            .get_property(false, window, AtomEnum::WM_NAME, AtomEnum::STRING, 0, u32::MAX)?
+            .reply()?
+            .value;
+    }
+
+    // Instance and class, each null-terminated
+    let app_ids = conn
+        .get_property(false, window, AtomEnum::WM_CLASS, AtomEnum::STRING, 0, u32::MAX)?
+        .reply()?
+        .value
+        .split(|&byte| byte == 0)
+        .filter(|part| !part.is_empty())
+        .map(|part| String::from_utf8_lossy(part).to_string())
+        .collect();
+
+    let pid = conn
+        .get_property(false, window, atoms.net_wm_pid, AtomEnum::CARDINAL, 0, 1)?
+        .reply()?
+        .value32()
+        .and_then(|mut values| values.next());
+
+    Ok(WindowInfo {
+        title: String::from_utf8_lossy(&title).to_string(),
+        app_ids,
+        pid
+    })
+}
@@ -2,50 +2,10 @@ import { invoke } from "@tauri-apps/api";
 import { listen, type Event } from "@tauri-apps/api/event";
 import { get, writable } from "svelte/store";
 
-export interface Launcher {
-    name: string;
-    command: string;
-    finder: string;
-    finder_is_regex?: boolean;
-    image_path?: string;
-    css_background?: string;
-};
-
-export interface Config {
-    name: string;
-    theme: {
-        color: string;
-        style: string;
-    };
-    home: {
-        screens: {
-            widgets: {
-                name: string;
-                coords: {
-                    x: number;
-                    y: number;
-                    w: number;
-                    h: number;
-                };
-                props?: any;
-            }[];
-        }[];
-    };
-    launchers: Launcher[];
-    weather?: {
-        provider: 'OpenWeatherMap',
-        api_key: string;
-        lat: number;
-        long: number;
-    };
-    audio_device?: {
-        name: string;
-    };
-    remote_server: {
-        port: number;
-    };
-    needs_setup?: boolean;
-}
+// Config types are generated from the backend's config types (see
+// src-tauri/build.rs), so they can't drift from what the backend accepts
+import type { Config, Launcher } from "./config.gen";
+export type { Config, Launcher };
 
 interface ConfigEvent {
     Set: {
@@ -65,7 +25,8 @@ export const config = writable<Config>({
         screens: []
     },
     remote_server: {
-        port: 0
+        port: 0,
+        port_internal: 0
     },
 });
 
@@ -78,6 +39,14 @@ export const listenConfig = async () => {
     });
 }
 
+type AppEvent = 'Home';
+
+export const listenApp = async (callback: (event: AppEvent) => void) => {
+    return listen('app', (event: Event<AppEvent>) => {
+        callback(event.payload);
+    });
+}
+
 export const setConfig = async (newConfig: Config) => {
     await invoke('set_config', { newConfig });
 }
@@ -95,6 +64,17 @@ export interface ActiveInfo {
     uuid: string;
     name: string;
     agent: Agent;
+    input: InputStats;
+    rtt_ms: number | null;
+}
+
+export interface InputStats {
+    received: number;
+    played: number;
+    coalesced: number;
+    dropped: number;
+    stale: number;
+    latency_ms: number | null;
 }
 
 export const getPendingDevices = async (): Promise<AccessInfo[]> => {
@@ -112,4 +92,92 @@ export interface RemoteServerIp {
 
 export const getRemoteServerIps = async (): Promise<RemoteServerIp[]> => {
     return await invoke('get_remote_server_ips') as RemoteServerIp[];
-}
\ No newline at end of file
+}
+
+export interface ProfileInfo {
+    name: string;
+    active: boolean;
+    has_pin: boolean;
+}
+
+export const listProfiles = async (): Promise<ProfileInfo[]> => {
+    return await invoke('list_profiles') as ProfileInfo[];
+}
+
+export const createProfile = async (name: string, pin?: string) => {
+    await invoke('create_profile', { name, pin });
+}
+
+export const cloneProfile = async (from: string, to: string, fromPin?: string, toPin?: string) => {
+    await invoke('clone_profile', { from, fromPin, to, toPin });
+}
+
+export const switchProfile = async (name: string, pin?: string) => {
+    await invoke('switch_profile', { name, pin });
+}
+
+export const deleteProfile = async (name: string, pin?: string) => {
+    await invoke('delete_profile', { name, pin });
+}
+
+export const setProfilePin = async (name: string, pin?: string, newPin?: string) => {
+    await invoke('set_profile_pin', { name, pin, newPin });
+}
+
+export type ImportMode = 'Merge' | 'Replace';
+
+export const exportConfig = async (path: string, includeDevices: boolean) => {
+    await invoke('export_config', { path, includeDevices });
+}
+
+// A bundle's remote server identity only replaces this machine's when
+// replacing the config, or when merging with `replaceDevices` set.
+export const importConfig = async (path: string, mode: ImportMode, replaceDevices?: boolean) => {
+    await invoke('import_config', { path, mode, replaceDevices });
+}
+
+export type LauncherState = 'Running' | {
+    Exited: {
+        code?: number;
+        signal?: number;
+    };
+};
+
+export interface LauncherStatus {
+    name: string;
+    pid: number;
+    started: number;
+    state: LauncherState;
+}
+
+interface LauncherEvent {
+    State: {
+        status: LauncherStatus;
+    };
+}
+
+export const listRunning = async (): Promise<LauncherStatus[]> => {
+    return await invoke('list_running') as LauncherStatus[];
+}
+
+export const listenLauncher = async (callback: (status: LauncherStatus) => void) => {
+    return listen('launcher', (event: Event<LauncherEvent>) => {
+        callback(event.payload.State.status);
+    });
+}
+
+export const stopLauncher = async (name: string) => {
+    await invoke('stop_launcher', { name });
+}
+
+export const restartLauncher = async (name: string) => {
+    await invoke('restart_launcher', { name });
+}
+
+export const getLauncherLog = async (name: string, lines?: number): Promise<string[]> => {
+    return await invoke('get_launcher_log', { name, lines }) as string[];
+}
+
+export const fillLauncherIcons = async (): Promise<number> => {
+    return await invoke('fill_launcher_icons') as number;
+}
@@ -0,0 +1,259 @@
+// Generated from the Rust config types by src-tauri/build.rs, do not edit.
+
+export type Config = {
+    name: string;
+    theme: Theme;
+    home: Home;
+    launchers: Launcher[];
+    shell?: string | null;
+    weather?: Weather | null;
+    audio_device?: AudioDevice | null;
+    remote_server: RemoteServer;
+    bindings?: Bindings | null;
+    macros?: Macro[] | null;
+    needs_setup?: boolean | null;
+};
+
+export type Theme = {
+    color: string;
+    style: string;
+};
+
+export type Home = {
+    screens: Screen[];
+    foreground_on_home?: ForegroundOnHome | null;
+};
+
+export type Screen = {
+    widgets: Widget[];
+};
+
+export type Widget = {
+    name: "clock";
+    coords: Coords;
+    props?: ClockProps;
+} | {
+    name: "weather";
+    coords: Coords;
+    props?: WeatherProps;
+} | {
+    name: "audioVisualizer";
+    coords: Coords;
+    props?: AudioVisualizerProps;
+} | {
+    name: "player";
+    coords: Coords;
+    props?: any;
+} | {
+    name: "pomodoro";
+    coords: Coords;
+    props?: PomodoroProps;
+} | {
+    name: "todo";
+    coords: Coords;
+    props?: TodoProps;
+};
+
+export type Coords = {
+    x: number;
+    y: number;
+    w: number;
+    h: number;
+};
+
+export type ClockProps = {
+    xAlign: XAlign;
+    yAlign: YAlign;
+    format: string;
+};
+
+export type XAlign = "left" | "center" | "right";
+
+export type YAlign = "top" | "middle" | "bottom";
+
+export type WeatherProps = {
+    xAlign: XAlign;
+    yAlign: YAlign;
+    heading: WeatherItem;
+    subheadings: WeatherItem[];
+};
+
+export type WeatherItem = "current" | "forecast3hr" | "forecast6hr" | "sunset";
+
+export type AudioVisualizerProps = {
+    yAlign: YAlign;
+    amp: number;
+};
+
+export type PomodoroProps = {
+    xAlign: XAlign;
+};
+
+export type TodoProps = {
+    xAlign: XAlign;
+};
+
+export type ForegroundOnHome = "Keep" | "Minimize" | "Pause";
+
+export type Launcher = {
+    name: string;
+    command: string;
+    finder: string;
+    finder_is_regex?: boolean | null;
+    finder_app_id?: string | null;
+    finder_process_name?: string | null;
+    finder_exe?: string | null;
+    image_path?: string | null;
+    css_background?: string | null;
+    args?: string[] | null;
+    env?: { [key: string]: string } | null;
+    cwd?: string | null;
+    use_shell?: boolean | null;
+};
+
+export type Weather = {
+    provider: WeatherProvider;
+    api_key?: Secret | null;
+    lat: number;
+    long: number;
+};
+
+export type WeatherProvider = "OpenWeatherMap";
+
+export type Secret = {
+    secret: string;
+} | string;
+
+export type AudioDevice = {
+    name: string;
+};
+
+export type RemoteServer = {
+    port: number;
+    port_internal: number;
+    port_app?: number | null;
+    input_backend?: InputBackend | null;
+    pointer?: Pointer | null;
+};
+
+export type InputBackend = "Auto" | "Tfc" | "Uinput";
+
+export type Pointer = {
+    speed?: number | null;
+    acceleration?: number | null;
+};
+
+export type Bindings = {
+    default?: BindingSet | null;
+    launchers?: { [key: string]: BindingSet } | null;
+};
+
+export type BindingSet = {
+    dpad?: { [key: string]: Binding } | null;
+    actions?: { [key: string]: Binding } | null;
+};
+
+export type Binding = {
+    Keys: KeyChord[];
+} | {
+    Launcher: string;
+} | {
+    Command: string;
+} | "Home";
+
+export type KeyChord = {
+    modifiers?: Modifier[] | null;
+    key: Key;
+};
+
+export type Modifier = "Shift" | "Control" | "Alt" | "Meta";
+
+export type Key = "A" | "B" | "C" | "D" | "E" | "F" | "G" | "H" | "I" | "J" | "K" | "L" | "M" | "N" | "O" | "P" | "Q" | "R" | "S" | "T" | "U" | "V" | "W" | "X" | "Y" | "Z" | "Digit0" | "Digit1" | "Digit2" | "Digit3" | "Digit4" | "Digit5" | "Digit6" | "Digit7" | "Digit8" | "Digit9" | "F1" | "F2" | "F3" | "F4" | "F5" | "F6" | "F7" | "F8" | "F9" | "F10" | "F11" | "F12" | "Minus" | "Equal" | "LeftBracket" | "RightBracket" | "Backslash" | "Semicolon" | "Quote" | "Grave" | "Comma" | "Period" | "Slash" | "Tab" | "Enter" | "Escape" | "Space" | "Backspace" | "Delete" | "CapsLock" | "Up" | "Down" | "Left" | "Right" | "Home" | "End" | "PageUp" | "PageDown" | "Shift" | "Control" | "Alt" | "Meta";
+
+export type Macro = {
+    name: string;
+    icon?: string | null;
+    steps: MacroStep[];
+};
+
+export type MacroStep = {
+    Event: RemoteControlEvent;
+} | {
+    Run: Binding;
+} | {
+    Delay: {
+        ms: number;
+    };
+};
+
+export type RemoteControlEvent = {
+    DPad: DPadDirection;
+} | {
+    Text: string;
+} | {
+    Keyboard: Key;
+} | {
+    Chord: {
+        modifiers: Modifier[];
+        key: Key;
+    };
+} | {
+    Media: MediaKey;
+} | {
+    MouseMove: {
+        dx: number;
+        dy: number;
+    };
+} | {
+    MousePosition: {
+        x: number;
+        y: number;
+    };
+} | {
+    MouseDown: MouseButton;
+} | {
+    MouseUp: MouseButton;
+} | {
+    MouseClick: MouseButton;
+} | {
+    MouseScroll: {
+        dx: number;
+        dy: number;
+    };
+} | {
+    Action: Action;
+} | {
+    Macro: string;
+} | {
+    Gamepad: GamepadEvent;
+};
+
+export type DPadDirection = "Up" | "Down" | "Left" | "Right" | "Enter" | "Exit";
+
+export type MediaKey = "PlayPause" | "FastForward" | "Rewind" | "VolumeUp" | "VolumeDown" | "Mute";
+
+export type MouseButton = "LeftButton" | "RightButton" | "MiddleButton";
+
+export type Action = "Home" | "AltTab";
+
+export type GamepadEvent = {
+    Button: {
+        button: GamepadButton;
+        pressed: boolean;
+    };
+} | {
+    Stick: {
+        stick: Side;
+        x: number;
+        y: number;
+    };
+} | {
+    Trigger: {
+        trigger: Side;
+        value: number;
+    };
+};
+
+export type GamepadButton = "A" | "B" | "X" | "Y" | "LeftBumper" | "RightBumper" | "Back" | "Start" | "Guide" | "LeftStick" | "RightStick" | "DPadUp" | "DPadDown" | "DPadLeft" | "DPadRight";
+
+export type Side = "Left" | "Right";